@@ -1,16 +1,13 @@
 use std::fs::File;
 
-use sw_structure_io::structs::*;
+use sw_structure_io::builder::BuildingBuilder;
 use sw_structure_io::io::WriteBuilding;
 
 fn main() {
     let version = 0;
 
-    let mut building = Building::default();
-    let root = Root::default();
-    let mut block = Block::default();
-
-    building.roots.push(root);
+    let mut builder = BuildingBuilder::new();
+    let mut root = builder.add_root();
 
     let hello_world = [
         "#  #      # #          #     #           #    #",
@@ -24,13 +21,13 @@ fn main() {
         let string = hello_world[y];
         for x in 0..string.len() {
             if string.as_bytes()[x] == '#' as u8 {
-                block.position = [x as f32, 0f32 - (y as f32), 0f32];
-
-                building.blocks.push(block.clone());
+                root.add_block(0, [x as f32, 0f32 - (y as f32), 0f32], [0f32; 3]);
             }
         }
     }
 
+    let building = builder.build().unwrap();
+
     let mut file = File::create("example_building.structure").unwrap();
     file.write_building(&building, version).unwrap();
 }
\ No newline at end of file
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bounds::Bounds;
+use crate::id::BlockId;
+use crate::math;
+use crate::spatial::SpatialIndex;
+use crate::structs::*;
+use crate::union_find::UnionFind;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Size of every block type, along the block's local axes.
+///
+/// Types without an entry use the default size, e.g. `[1.0; 3]` for plain
+/// cubes.
+pub struct SizeTable {
+    default: [f32; 3],
+    sizes: HashMap<u8, [f32; 3]>,
+}
+
+impl Default for SizeTable {
+    fn default() -> Self {
+        Self::new([1.0; 3])
+    }
+}
+
+impl SizeTable {
+    pub fn new(default: [f32; 3]) -> Self {
+        Self { default, sizes: HashMap::new() }
+    }
+
+    /// Sets the size of a block type.
+    pub fn with_size(mut self, id: u8, size: [f32; 3]) -> Self {
+        self.sizes.insert(id, size);
+        self
+    }
+
+    /// Size of a block type, along its local axes.
+    pub fn size(&self, id: u8) -> [f32; 3] {
+        self.sizes.get(&id).copied().unwrap_or(self.default)
+    }
+
+    /// World-space box of a block, from its type, position and rotation.
+    pub fn block_bounds(&self, block: &Block) -> Bounds {
+        let half = self.size(block.id).map(|s| s * 0.5);
+        let m = math::euler_to_matrix(block.rotation);
+        // Extent of the rotated box along every world axis.
+        let extent = [0, 1, 2].map(|row| (0..3).map(|col| m[row][col].abs() * half[col]).sum::<f32>());
+        Bounds {
+            min: [0, 1, 2].map(|i| block.position[i] - extent[i]),
+            max: [0, 1, 2].map(|i| block.position[i] + extent[i]),
+        }
+    }
+}
+
+/// Returns `true` if two boxes share part of a face, or overlap.
+///
+/// Boxes touching only along an edge or at a corner are not adjacent.
+fn face_adjacent(a: &Bounds, b: &Bounds, tolerance: f32) -> bool {
+    // Negative gaps are overlaps.
+    let gaps = [0, 1, 2].map(|i| a.min[i].max(b.min[i]) - a.max[i].min(b.max[i]));
+    gaps.iter().all(|&gap| gap <= tolerance) && gaps.iter().filter(|&&gap| gap < -tolerance).count() >= 2
+}
+
+impl Building {
+    /// Pairs of blocks whose boxes share part of a face (or overlap), sorted.
+    ///
+    /// Boxes come from `sizes` and the block transforms; faces closer than
+    /// `tolerance` touch.
+    pub fn face_adjacency(&self, sizes: &SizeTable, tolerance: f32) -> Vec<(BlockId, BlockId)> {
+        let bounds: Vec<Bounds> = self.blocks.iter().map(|block| sizes.block_bounds(block)).collect();
+        let largest = bounds
+            .iter()
+            .flat_map(|b| (0..3).map(|i| b.max[i] - b.min[i]))
+            .fold(f32::EPSILON, f32::max);
+        let index = SpatialIndex::new(self, largest);
+
+        let mut pairs = Vec::new();
+        for (id, block_bounds) in bounds.iter().enumerate() {
+            // Any adjacent block has its position within this box.
+            let around = Bounds {
+                min: block_bounds.min.map(|v| v - largest / 2.0 - tolerance),
+                max: block_bounds.max.map(|v| v + largest / 2.0 + tolerance),
+            };
+            for other in index.in_box(&around) {
+                if other.index() > id && face_adjacent(block_bounds, &bounds[other.index()], tolerance) {
+                    pairs.push((BlockId(id as u16), other));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Replaces the roots with one root per group of face-adjacent blocks,
+    /// see [`Building::face_adjacency`].
+    ///
+    /// Blocks linked through `load` (bearings and the like) are never put in
+    /// the same root, since that link is a joint between rigid bodies: an
+    /// adjacency that would join both ends of a link, even through other
+    /// blocks, is ignored. Adjacencies are taken in the order of
+    /// [`Building::face_adjacency`]. Roots are ordered by their first block. Every new root is placed at the
+    /// center of its blocks, and keeps the rotation of the old root of its
+    /// first block.
+    ///
+    /// Returns the blocks of every new root.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    /// use sw_structure_io::adjacency::SizeTable;
+    ///
+    /// let mut building = Building::default();
+    /// building.roots.push(Root::default());
+    /// for x in [0.0, 1.0, 5.0] {
+    ///     building.blocks.push(Block { position: [x, 0.0, 0.0], ..Default::default() });
+    /// }
+    ///
+    /// let roots = building.infer_roots(&SizeTable::default(), 1e-3);
+    /// assert_eq!(roots.len(), 2);
+    /// assert_eq!(building.blocks.iter().map(|b| b.root).collect::<Vec<_>>(), [0, 0, 1]);
+    /// assert_eq!(building.roots[0].position, [0.5, 0.0, 0.0]);
+    /// ```
+    pub fn infer_roots(&mut self, sizes: &SizeTable, tolerance: f32) -> Vec<Vec<BlockId>> {
+        // Both ends of every load link, and the members of every group.
+        let mut linked: Vec<Vec<usize>> = vec![Vec::new(); self.blocks.len()];
+        for (id, block) in self.iter_blocks() {
+            if let Some(load) = block.load_id().filter(|load| load.index() < self.blocks.len()) {
+                linked[id.index()].push(load.index());
+                linked[load.index()].push(id.index());
+            }
+        }
+        let mut members: Vec<Vec<usize>> = (0..self.blocks.len()).map(|i| vec![i]).collect();
+
+        let mut groups = UnionFind::new(self.blocks.len());
+        for (a, b) in self.face_adjacency(sizes, tolerance) {
+            let (a, b) = (groups.find(a.index()), groups.find(b.index()));
+            if a == b {
+                continue;
+            }
+            let joins_link = members[a.max(b)]
+                .iter()
+                .any(|&member| linked[member].iter().any(|&other| groups.find(other) == a.min(b)));
+            if !joins_link {
+                groups.union(a, b);
+                let moved = std::mem::take(&mut members[a.max(b)]);
+                members[a.min(b)].extend(moved);
+            }
+        }
+
+        let components: Vec<Vec<BlockId>> = groups
+            .sets()
+            .into_iter()
+            .map(|set| set.into_iter().map(|i| BlockId(i as u16)).collect())
+            .collect();
+
+        let roots: Vec<Root> = components
+            .iter()
+            .map(|blocks| {
+                let bounds = Bounds::from_points(blocks.iter().map(|b| &self.blocks[b.index()].position));
+                let (center, _) = bounds.get_center_and_size();
+                let old_root = self.root_of(blocks[0]);
+                Root { position: center, rotation: old_root.map(|r| r.rotation).unwrap_or_default() }
+            })
+            .collect();
+
+        for (root, blocks) in components.iter().enumerate() {
+            for block in blocks {
+                self.blocks[block.index()].root = u16::try_from(root).expect("too many roots");
+            }
+        }
+        self.roots = roots;
+
+        components
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Cursor, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use sw_structure_io::io::{ReadBuilding, WriteBuilding, inspect_building};
+use sw_structure_io::diff::diff;
+use sw_structure_io::structs::*;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const USAGE: &str = "\
+Usage: sw-structure <command> [arguments]
+
+Commands:
+    info <file>                                 Print version, counts, bounds and block-type histogram
+    convert <input> <output> [--version <n>]    Convert between format versions and JSON
+    validate <file> [--version <n>]             Check index references and serializability
+    dump <file>                                 Print the building as JSON
+    inspect <file>                              Print an annotated hex dump of a binary file
+    diff <old> <new>                            Print added, removed and changed roots and blocks
+    graph <file>                                Print the connection graph in Graphviz DOT format
+
+Files ending in `.json` are read and written as JSON, everything else as
+binary building files. When converting to a binary file without `--version`,
+the version of the input file is kept (or 0 when the input is JSON).";
+
+/// A building loaded from disk, along with the binary version it was stored in.
+struct Loaded {
+    version: Option<u8>,
+    building: Building,
+}
+
+fn is_json(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+fn load(path: &str) -> Result<Loaded> {
+    if is_json(path) {
+        let building = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        return Ok(Loaded { version: None, building });
+    }
+
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    let version = bytes.first().copied();
+    let building = Cursor::new(bytes).read_building()?;
+
+    Ok(Loaded { version, building })
+}
+
+fn save(path: &str, building: &Building, version: u8) -> Result<()> {
+    // Serialize into memory first, so a failed write does not leave a partial file behind.
+    let mut buffer = Vec::new();
+    if is_json(path) {
+        serde_json::to_writer_pretty(&mut buffer, building)?;
+    } else {
+        buffer.write_building(building, version)?;
+    }
+
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(&buffer)?;
+    file.flush()?;
+    Ok(())
+}
+
+fn parse_version(args: &[String]) -> Result<Option<u8>> {
+    match args.iter().position(|a| a == "--version") {
+        Some(i) => {
+            let value = args.get(i + 1).ok_or("`--version` expects a value")?;
+            Ok(Some(value.parse()?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Options followed by a value.
+const OPTIONS: &[&str] = &["--version"];
+
+fn positional(args: &[String]) -> Result<Vec<&str>> {
+    let mut result = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if OPTIONS.contains(&arg.as_str()) {
+            iter.next();
+        } else if arg.starts_with("--") {
+            return Err(format!("Unknown option `{arg}`\n\n{USAGE}").into());
+        } else {
+            result.push(arg.as_str());
+        }
+    }
+    Ok(result)
+}
+
+fn info(path: &str) -> Result<()> {
+    let Loaded { version, building } = load(path)?;
+    let mut out = std::io::stdout().lock();
+
+    match version {
+        Some(version) => writeln!(out, "Version: {version}")?,
+        None => writeln!(out, "Version: - (JSON)")?,
+    }
+    writeln!(out, "Roots:   {}", building.roots.len())?;
+    writeln!(out, "Blocks:  {}", building.blocks.len())?;
+
+    let bounds = building.bounds();
+    if !bounds.is_empty() {
+        writeln!(out, "Bounds:  min {:?}, max {:?}", bounds.min, bounds.max)?;
+    }
+
+    let mut histogram: BTreeMap<u8, usize> = BTreeMap::new();
+    for block in building.blocks.iter() {
+        *histogram.entry(block.id).or_default() += 1;
+    }
+    if !histogram.is_empty() {
+        writeln!(out, "Block types:")?;
+        for (id, count) in histogram {
+            writeln!(out, "    {id:>3}: {count}")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn convert(input: &str, output: &str, version: Option<u8>) -> Result<()> {
+    let loaded = load(input)?;
+    let version = version.or(loaded.version).unwrap_or(0);
+    save(output, &loaded.building, version)
+}
+
+fn validate(path: &str, version: Option<u8>) -> Result<bool> {
+    let loaded = load(path)?;
+    let mut out = std::io::stdout().lock();
+    let mut valid = true;
+
+    for issue in loaded.building.validate() {
+        writeln!(out, "{issue}")?;
+        valid = false;
+    }
+
+    let version = version.or(loaded.version).unwrap_or(0);
+    if let Err(e) = Vec::new().write_building(&loaded.building, version) {
+        writeln!(out, "Building can not be written as version {version}: {e}")?;
+        valid = false;
+    }
+
+    if valid {
+        writeln!(out, "OK")?;
+    }
+
+    Ok(valid)
+}
+
+fn dump(path: &str) -> Result<()> {
+    let loaded = load(path)?;
+    let mut out = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut out, &loaded.building)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn inspect(path: &str) -> Result<bool> {
+    let inspection = inspect_building(BufReader::new(File::open(path)?));
+    write!(std::io::stdout().lock(), "{inspection}")?;
+    Ok(inspection.error.is_none() && inspection.trailing == 0)
+}
+
+fn diff_files(old: &str, new: &str) -> Result<bool> {
+    let difference = diff(&load(old)?.building, &load(new)?.building);
+    write!(std::io::stdout().lock(), "{difference}")?;
+    Ok(difference.is_empty())
+}
+
+fn graph(path: &str) -> Result<()> {
+    let building = load(path)?.building;
+    write!(std::io::stdout().lock(), "{}", building.connection_graph().to_dot(&building))?;
+    Ok(())
+}
+
+fn run(args: &[String]) -> Result<bool> {
+    let Some(command) = args.first() else {
+        return Err(USAGE.into());
+    };
+    if command == "help" || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        writeln!(std::io::stdout().lock(), "{USAGE}")?;
+        return Ok(true);
+    }
+    let rest = &args[1..];
+    let files = positional(rest)?;
+    let version = parse_version(rest)?;
+
+    match (command.as_str(), files.as_slice()) {
+        ("info", [file]) => info(file).map(|_| true),
+        ("convert", [input, output]) => convert(input, output, version).map(|_| true),
+        ("validate", [file]) => validate(file, version),
+        ("dump", [file]) => dump(file).map(|_| true),
+        ("inspect", [file]) => inspect(file),
+        ("diff", [old, new]) => diff_files(old, new),
+        ("graph", [file]) => graph(file).map(|_| true),
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn is_broken_pipe(e: &(dyn std::error::Error + 'static)) -> bool {
+    let kind = match e.downcast_ref::<serde_json::Error>() {
+        Some(e) => e.io_error_kind(),
+        None => e.downcast_ref::<std::io::Error>().map(std::io::Error::kind),
+    };
+    kind == Some(std::io::ErrorKind::BrokenPipe)
+}
+
+fn main() -> ExitCode {
+    env_logger::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        // The reader went away, e.g. `sw-structure inspect file | head`.
+        Err(e) if is_broken_pipe(e.as_ref()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
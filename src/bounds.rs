@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use crate::id::RootId;
+use crate::structs::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+/// An axis-aligned bounding box.
+///
+/// Newer formats store block positions as `i16` offsets inside the bounds of
+/// their root, so the size of a root's bounds decides how precisely its
+/// blocks are stored (see [`Bounds::to_inbounds`]).
+///
+/// The default bounds are empty (`min` is infinite, `max` negative infinite)
+/// and grow with [`Bounds::encapsulate`].
+pub struct Bounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Bounds {
+    /// Bounds centered on `center`, `size` wide along every axis.
+    pub const fn from_center_and_size(center: [f32; 3], size: [f32; 3]) -> Self {
+        let mut min = [0.0f32; 3];
+        let mut max = [0.0f32; 3];
+
+        let mut i = 0;
+        while i < 3 {
+            min[i] = center[i] - size[i] * 0.5;
+            max[i] = center[i] + size[i] * 0.5;
+            i += 1;
+        }
+
+        Self { min, max }
+    }
+
+    /// Center and size of the bounds, the inverse of
+    /// [`Bounds::from_center_and_size`].
+    pub const fn get_center_and_size(&self) -> ([f32; 3], [f32; 3]) {
+        let mut center = [0.0f32; 3];
+        let mut size = [0.0f32; 3];
+
+        let mut i = 0;
+        while i < 3 {
+            center[i] = (self.min[i] + self.max[i]) * 0.5;
+            size[i] = self.max[i] - self.min[i];
+            i += 1;
+        }
+
+        (center, size)
+    }
+
+    /// Bounds of the given points, empty if there are none.
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a [f32; 3]>) -> Self {
+        let mut bounds = Self::default();
+        for point in points {
+            bounds.encapsulate(point);
+        }
+        bounds
+    }
+
+    /// Returns `true` if no point was added to the bounds.
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|i| self.min[i] > self.max[i])
+    }
+
+    /// Returns `true` if `point` lies inside the bounds, borders included.
+    pub fn contains(&self, point: &[f32; 3]) -> bool {
+        (0..3).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+
+    /// Quantizes a position into the `i16` offset stored by newer formats.
+    pub fn to_inbounds(&self, f: [f32; 3]) -> [i16; 3] {
+        let (center, size) = self.get_center_and_size();
+
+        let mut result = [0i16; 3];
+        for i in 0..3 {
+            let multiplier = (1.0f32 / size[i]) * i16::MAX as f32;
+            result[i] = ((f[i] - center[i]) * multiplier).round() as i16
+        }
+        result
+    }
+
+    /// Restores a position from its quantized `i16` offset.
+    pub fn to_global(&self, v: [i16; 3]) -> [f32; 3] {
+        let (center, size) = self.get_center_and_size();
+
+        let mut result = [0.0f32; 3];
+        for i in 0..3 {
+            let multiplier = size[i] / i16::MAX as f32;
+            result[i] = center[i] + v[i] as f32 * multiplier;
+        }
+        result
+    }
+
+    /// Grows the bounds to include `block_position`.
+    pub fn encapsulate(&mut self, block_position: &[f32; 3]) {
+        for (i, &v) in block_position.iter().enumerate() {
+            self.min[i] = self.min[i].min(v);
+            self.max[i] = self.max[i].max(v);
+        }
+    }
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Bounds {
+            max: [f32::NEG_INFINITY; 3],
+            min: [f32::INFINITY; 3]
+        }
+    }
+}
+
+impl Building {
+    /// Bounds of all block positions.
+    pub fn bounds(&self) -> Bounds {
+        Bounds::from_points(self.blocks.iter().map(|block| &block.position))
+    }
+
+    /// Bounds of the block positions of a root, as used to quantize them.
+    pub fn root_bounds(&self, root: RootId) -> Bounds {
+        Bounds::from_points(self.blocks_of(root).map(|(_, block)| &block.position))
+    }
+
+    /// Bounds of the block positions of every root, in root order.
+    pub fn roots_bounds(&self) -> Vec<Bounds> {
+        let mut bounds = vec![Bounds::default(); self.roots.len()];
+        for block in self.blocks.iter() {
+            if let Some(b) = bounds.get_mut(block.root as usize) {
+                b.encapsulate(&block.position);
+            }
+        }
+        bounds
+    }
+}
@@ -0,0 +1,168 @@
+use crate::id::{BlockId, RootId};
+use crate::structs::*;
+use crate::validate::ValidationIssue;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+/// Error returned by [`BuildingBuilder::build`].
+pub enum BuildError {
+    #[error("Building has {} validation issues, the first is: {}", issues.len(), issues[0])]
+    Invalid {
+        issues: Vec<ValidationIssue>,
+        building: Box<Building>,
+    },
+}
+
+#[derive(Clone, Debug, Default)]
+/// Incremental construction of a [`Building`] through handles.
+///
+/// Roots and blocks are referred to by the [`RootId`] and [`BlockId`]
+/// handles returned when adding them, so no index has to be tracked by hand.
+/// [`BuildingBuilder::build`] validates the result.
+///
+/// # Example
+/// ```rust
+/// use sw_structure_io::builder::BuildingBuilder;
+///
+/// let mut builder = BuildingBuilder::new();
+/// let mut root = builder.add_root();
+/// let button = root.add_block(0, [0.0, 0.0, 0.0], [0.0, 0.0, 0.0]);
+/// let light = root.add_block(1, [1.0, 0.0, 0.0], [0.0, 0.0, 0.0]);
+///
+/// builder.connect(button, light).color(light, [255, 0, 0, 255]);
+/// let building = builder.build().unwrap();
+/// assert_eq!(building.blocks[0].connections, vec![1]);
+/// ```
+pub struct BuildingBuilder {
+    building: Building,
+}
+
+/// A root of a [`BuildingBuilder`], used to add blocks to it.
+pub struct RootBuilder<'a> {
+    builder: &'a mut BuildingBuilder,
+    root: RootId,
+}
+
+impl BuildingBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continues building on an existing building.
+    pub fn from_building(building: Building) -> Self {
+        Self { building }
+    }
+
+    /// Adds a root with the default transform.
+    pub fn add_root(&mut self) -> RootBuilder<'_> {
+        let root = self.building.push_root(Root::default());
+        self.root(root)
+    }
+
+    /// Returns an already added root.
+    ///
+    /// # Panics
+    /// Panics if `root` does not exist.
+    pub fn root(&mut self, root: RootId) -> RootBuilder<'_> {
+        assert!(self.building.root(root).is_some(), "root {root} does not exist");
+        RootBuilder { builder: self, root }
+    }
+
+    /// Adds a block of type `kind` to `root`.
+    ///
+    /// # Panics
+    /// Panics if the building already has `u16::MAX` blocks.
+    pub fn add_block(&mut self, root: RootId, kind: u8, position: [f32; 3], rotation: [f32; 3]) -> BlockId {
+        self.building.push_block(Block {
+            id: kind,
+            root: root.into(),
+            position,
+            rotation,
+            ..Default::default()
+        })
+    }
+
+    /// Returns a block for changes not covered by the builder, e.g. metadata.
+    ///
+    /// # Panics
+    /// Panics if `block` does not exist.
+    pub fn block_mut(&mut self, block: BlockId) -> &mut Block {
+        self.building.block_mut(block).unwrap_or_else(|| panic!("block {block} does not exist"))
+    }
+
+    /// Connects `from` to `to`, by adding `to` to the connections of `from`.
+    pub fn connect(&mut self, from: BlockId, to: BlockId) -> &mut Self {
+        self.block_mut(from).connections.push(to.into());
+        self
+    }
+
+    /// Sets `load` as the block mechanically attached to `block` (e.g. the
+    /// other side of a bearing). Both blocks should be on different roots.
+    pub fn attach_load(&mut self, block: BlockId, load: BlockId) -> &mut Self {
+        self.block_mut(block).load = Some(load.into());
+        self
+    }
+
+    /// Sets the color of a block.
+    pub fn color(&mut self, block: BlockId, color: [u8; 4]) -> &mut Self {
+        self.block_mut(block).color = Some(color);
+        self
+    }
+
+    /// Sets the name of a block.
+    pub fn name(&mut self, block: BlockId, name: impl Into<String>) -> &mut Self {
+        self.block_mut(block).name = name.into();
+        self
+    }
+
+    /// Sets the metadata of a block.
+    pub fn metadata(&mut self, block: BlockId, metadata: Metadata) -> &mut Self {
+        self.block_mut(block).metadata = Some(metadata);
+        self
+    }
+
+    /// Validates and returns the building.
+    ///
+    /// On validation issues, the error holds both the issues and the
+    /// building, so nothing is lost.
+    pub fn build(self) -> Result<Building, BuildError> {
+        let issues = self.building.validate();
+        if issues.is_empty() {
+            Ok(self.building)
+        } else {
+            Err(BuildError::Invalid { issues, building: Box::new(self.building) })
+        }
+    }
+
+    /// Returns the building without validating it.
+    pub fn build_unchecked(self) -> Building {
+        self.building
+    }
+}
+
+impl RootBuilder<'_> {
+    /// Handle of this root.
+    pub fn id(&self) -> RootId {
+        self.root
+    }
+
+    /// Sets the world-space position of the root.
+    pub fn position(&mut self, position: [f32; 3]) -> &mut Self {
+        self.root_mut().position = position;
+        self
+    }
+
+    /// Sets the world-space rotation of the root.
+    pub fn rotation(&mut self, rotation: [f32; 3]) -> &mut Self {
+        self.root_mut().rotation = rotation;
+        self
+    }
+
+    /// Adds a block of type `kind` to this root.
+    pub fn add_block(&mut self, kind: u8, position: [f32; 3], rotation: [f32; 3]) -> BlockId {
+        self.builder.add_block(self.root, kind, position, rotation)
+    }
+
+    fn root_mut(&mut self) -> &mut Root {
+        self.builder.building.root_mut(self.root).expect("root handles of a builder are valid")
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+
+use crate::structs::*;
+
+/// Positions closer than this are considered equal when matching blocks.
+pub const POSITION_TOLERANCE: f32 = 1e-3;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Correspondence between the roots and blocks of two buildings.
+///
+/// Blocks are matched by type id and position (within [`POSITION_TOLERANCE`]),
+/// never by index, so reordering blocks does not produce any differences.
+/// Roots are matched by the roots their matched blocks belong to, falling
+/// back to equal transforms for roots without matched blocks.
+pub struct Matching {
+    /// For every block of the old building, index of the matching new block.
+    pub blocks: Vec<Option<usize>>,
+
+    /// For every root of the old building, index of the matching new root.
+    pub roots: Vec<Option<usize>>,
+}
+
+impl Matching {
+    /// Maps an old block reference to the new building.
+    pub fn block(&self, old: u16) -> Option<u16> {
+        self.blocks.get(old as usize).copied().flatten().and_then(|i| i.try_into().ok())
+    }
+
+    /// Maps an old root reference to the new building.
+    pub fn root(&self, old: u16) -> Option<u16> {
+        self.roots.get(old as usize).copied().flatten().and_then(|i| i.try_into().ok())
+    }
+
+    /// Inverse of `blocks`: for every new block, the matching old block.
+    pub fn new_blocks(&self, new_count: usize) -> Vec<Option<usize>> {
+        invert(&self.blocks, new_count)
+    }
+
+    /// Inverse of `roots`: for every new root, the matching old root.
+    pub fn new_roots(&self, new_count: usize) -> Vec<Option<usize>> {
+        invert(&self.roots, new_count)
+    }
+}
+
+fn invert(map: &[Option<usize>], count: usize) -> Vec<Option<usize>> {
+    let mut inverse = vec![None; count];
+    for (old, new) in map.iter().enumerate() {
+        if let Some(new) = *new {
+            inverse[new] = Some(old);
+        }
+    }
+    inverse
+}
+
+type BlockKey = (u8, [i64; 3]);
+
+/// Type id and grid cell of a block. Cells are `POSITION_TOLERANCE` wide, so
+/// positions within tolerance of each other are in the same or neighbouring
+/// cells. `None` if a coordinate is not finite, or too large for its cell to
+/// fit an `i64`.
+fn block_key(block: &Block) -> Option<BlockKey> {
+    let cell = block.position.map(|v| (v / POSITION_TOLERANCE).floor());
+    cell.iter().all(|c| c.abs() < 2f32.powi(62)).then(|| (block.id, cell.map(|c| c as i64)))
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum::<f32>().sqrt()
+}
+
+/// Matches the roots and blocks of two buildings.
+pub fn match_buildings(old: &Building, new: &Building) -> Matching {
+    let mut candidates: HashMap<BlockKey, Vec<usize>> = HashMap::new();
+    // New blocks without a cell, compared with every old block without one.
+    let mut unkeyed = Vec::new();
+    for (index, block) in new.blocks.iter().enumerate() {
+        match block_key(block) {
+            Some(key) => candidates.entry(key).or_default().push(index),
+            None => unkeyed.push(index),
+        }
+    }
+    // Every old block takes the closest free new block within tolerance,
+    // searching its cell and the neighbouring ones. Equally close blocks are
+    // matched in order of appearance.
+    let mut taken = vec![false; new.blocks.len()];
+    let blocks: Vec<Option<usize>> = old.blocks
+        .iter()
+        .map(|block| {
+            let nearby: Vec<usize> = match block_key(block) {
+                Some((id, cell)) => (0..27)
+                    .filter_map(|offset| {
+                        let neighbour = [offset % 3, offset / 3 % 3, offset / 9].map(|o| o as i64 - 1);
+                        let [x, y, z] = [0, 1, 2].map(|i| cell[i].checked_add(neighbour[i]));
+                        candidates.get(&(id, [x?, y?, z?]))
+                    })
+                    .flatten()
+                    .copied()
+                    .collect(),
+                None => unkeyed.iter().copied().filter(|&index| new.blocks[index].id == block.id).collect(),
+            };
+            let mut best: Option<(f32, usize)> = None;
+            for index in nearby {
+                let position = new.blocks[index].position;
+                // Identical positions match even when they are infinite.
+                let distance = if position == block.position { 0.0 } else { distance(block.position, position) };
+                if taken[index] || distance.is_nan() || distance > POSITION_TOLERANCE {
+                    continue;
+                }
+                if best.is_none_or(|best| (distance, index) < best) {
+                    best = Some((distance, index));
+                }
+            }
+            let (_, index) = best?;
+            taken[index] = true;
+            Some(index)
+        })
+        .collect();
+
+    // Roots are paired by how many matched blocks they share.
+    let mut votes: HashMap<(usize, usize), usize> = HashMap::new();
+    for (old_index, new_index) in blocks.iter().enumerate() {
+        if let Some(new_index) = *new_index {
+            let pair = (old.blocks[old_index].root as usize, new.blocks[new_index].root as usize);
+            *votes.entry(pair).or_default() += 1;
+        }
+    }
+    let mut votes: Vec<((usize, usize), usize)> = votes.into_iter().collect();
+    votes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut roots: Vec<Option<usize>> = vec![None; old.roots.len()];
+    let mut taken = vec![false; new.roots.len()];
+    for ((old_root, new_root), _) in votes {
+        if old_root < roots.len() && new_root < taken.len() && roots[old_root].is_none() && !taken[new_root] {
+            roots[old_root] = Some(new_root);
+            taken[new_root] = true;
+        }
+    }
+
+    for (old_root, matched) in roots.iter_mut().enumerate() {
+        if matched.is_some() {
+            continue;
+        }
+        let found = new.roots
+            .iter()
+            .enumerate()
+            .position(|(i, r)| !taken[i] && *r == old.roots[old_root]);
+        if let Some(new_root) = found {
+            *matched = Some(new_root);
+            taken[new_root] = true;
+        }
+    }
+
+    Matching { blocks, roots }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A single changed field of a matched root or block.
+pub struct FieldChange {
+    /// Path of the field, e.g. `metadata.values`.
+    pub path: String,
+
+    /// Old value, formatted for humans. References are old indices.
+    pub old: String,
+
+    /// New value, formatted for humans. References are new indices.
+    pub new: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Difference of a single root or block between two buildings.
+pub enum Change {
+    /// Present only in the new building.
+    Added { new: usize },
+
+    /// Present only in the old building.
+    Removed { old: usize },
+
+    /// Present in both buildings, with at least one differing field.
+    Changed { old: usize, new: usize, fields: Vec<FieldChange> },
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Semantic difference between two buildings, produced by [`diff`].
+pub struct BuildingDiff {
+    pub roots: Vec<Change>,
+    pub blocks: Vec<Change>,
+}
+
+impl BuildingDiff {
+    /// Returns `true` if both buildings are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty() && self.blocks.is_empty()
+    }
+}
+
+/// Collects field changes, mapping references of the old side into the new building.
+struct Comparer<'a> {
+    matching: &'a Matching,
+    fields: Vec<FieldChange>,
+}
+
+impl Comparer<'_> {
+    fn push(&mut self, path: &str, old: String, new: String) {
+        self.fields.push(FieldChange { path: path.to_string(), old, new });
+    }
+
+    fn compare<T: PartialEq + Debug>(&mut self, path: &str, old: &T, new: &T) {
+        if old != new {
+            self.push(path, format!("{old:?}"), format!("{new:?}"));
+        }
+    }
+
+    fn compare_blocks<T: Copy + Debug + Into<u16>>(&mut self, path: &str, old: &[T], new: &[T]) {
+        let mapped = old.iter().map(|&i| self.matching.block(i.into()));
+        if !mapped.eq(new.iter().map(|&i| Some(i.into()))) {
+            self.push(path, format!("{old:?}"), format!("{new:?}"));
+        }
+    }
+}
+
+/// Computes the semantic difference between two buildings.
+///
+/// Blocks and roots are matched with [`match_buildings`]. Index references
+/// (`root`, `connections`, `load`, metadata fields and math block ordering)
+/// are compared through that matching, so renumbering alone is not a change.
+///
+/// # Example
+/// ```rust
+/// use sw_structure_io::structs::*;
+/// use sw_structure_io::diff::diff;
+///
+/// let old = Building::default();
+/// let mut new = Building::default();
+/// new.roots.push(Root::default());
+///
+/// assert_eq!(diff(&old, &new).roots.len(), 1);
+/// ```
+pub fn diff(old: &Building, new: &Building) -> BuildingDiff {
+    let matching = match_buildings(old, new);
+    let mut result = BuildingDiff::default();
+
+    for (old_index, new_index) in matching.roots.iter().enumerate() {
+        let Some(new_index) = *new_index else {
+            result.roots.push(Change::Removed { old: old_index });
+            continue;
+        };
+        let mut comparer = Comparer { matching: &matching, fields: Vec::new() };
+        comparer.compare("position", &old.roots[old_index].position, &new.roots[new_index].position);
+        comparer.compare("rotation", &old.roots[old_index].rotation, &new.roots[new_index].rotation);
+        if !comparer.fields.is_empty() {
+            result.roots.push(Change::Changed { old: old_index, new: new_index, fields: comparer.fields });
+        }
+    }
+    for (new_index, old_index) in matching.new_roots(new.roots.len()).iter().enumerate() {
+        if old_index.is_none() {
+            result.roots.push(Change::Added { new: new_index });
+        }
+    }
+
+    for (old_index, new_index) in matching.blocks.iter().enumerate() {
+        let Some(new_index) = *new_index else {
+            result.blocks.push(Change::Removed { old: old_index });
+            continue;
+        };
+        let fields = diff_block(&matching, &old.blocks[old_index], &new.blocks[new_index]);
+        if !fields.is_empty() {
+            result.blocks.push(Change::Changed { old: old_index, new: new_index, fields });
+        }
+    }
+    for (new_index, old_index) in matching.new_blocks(new.blocks.len()).iter().enumerate() {
+        if old_index.is_none() {
+            result.blocks.push(Change::Added { new: new_index });
+        }
+    }
+
+    result
+}
+
+fn diff_block(matching: &Matching, old: &Block, new: &Block) -> Vec<FieldChange> {
+    let mut c = Comparer { matching, fields: Vec::new() };
+
+    // Matched positions may differ within tolerance without being a change.
+    if distance(old.position, new.position) > POSITION_TOLERANCE {
+        c.push("position", format!("{:?}", old.position), format!("{:?}", new.position));
+    }
+    c.compare("rotation", &old.rotation, &new.rotation);
+    if matching.root(old.root) != Some(new.root) {
+        c.push("root", old.root.to_string(), new.root.to_string());
+    }
+    c.compare("name", &old.name, &new.name);
+    c.compare("enable_state", &old.enable_state, &new.enable_state);
+    c.compare("enable_state_current", &old.enable_state_current, &new.enable_state_current);
+    c.compare_blocks("connections", &old.connections, &new.connections);
+    c.compare_blocks("load", old.load.as_slice(), new.load.as_slice());
+    c.compare("color", &old.color, &new.color);
+
+    match (&old.metadata, &new.metadata) {
+        (Some(old), Some(new)) => diff_metadata(&mut c, old, new),
+        (old, new) => c.compare("metadata", &old.is_some(), &new.is_some()),
+    }
+
+    c.fields
+}
+
+fn diff_metadata(c: &mut Comparer, old: &Metadata, new: &Metadata) {
+    c.compare("metadata.toggles", &old.toggles, &new.toggles);
+    c.compare("metadata.values", &old.values, &new.values);
+    c.compare("metadata.fields.len", &old.fields.len(), &new.fields.len());
+    for (i, (old, new)) in old.fields.iter().zip(new.fields.iter()).enumerate() {
+        c.compare_blocks(&format!("metadata.fields[{i}]"), old, new);
+    }
+    c.compare("metadata.dropdowns", &old.dropdowns, &new.dropdowns);
+    c.compare("metadata.colors", &old.colors, &new.colors);
+    c.compare("metadata.gradients", &old.gradients, &new.gradients);
+    c.compare("metadata.vectors", &old.vectors, &new.vectors);
+
+    match (&old.type_settings, &new.type_settings) {
+        (
+            TypeSettings::MathBlock { function: old_function, incoming_connections_order: old_order, slots: old_slots },
+            TypeSettings::MathBlock { function: new_function, incoming_connections_order: new_order, slots: new_slots },
+        ) => {
+            c.compare("metadata.type_settings.function", old_function, new_function);
+            c.compare_blocks("metadata.type_settings.incoming_connections_order", old_order, new_order);
+            c.compare("metadata.type_settings.slots", old_slots, new_slots);
+        }
+        (old, new) => c.compare("metadata.type_settings", old, new),
+    }
+}
+
+impl Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.path, self.old, self.new)
+    }
+}
+
+fn write_changes(f: &mut fmt::Formatter<'_>, kind: &str, changes: &[Change]) -> fmt::Result {
+    for change in changes {
+        match change {
+            Change::Added { new } => writeln!(f, "+ {kind} {new}")?,
+            Change::Removed { old } => writeln!(f, "- {kind} {old}")?,
+            Change::Changed { old, new, fields } => {
+                writeln!(f, "~ {kind} {old} -> {new}")?;
+                for field in fields {
+                    writeln!(f, "    {field}")?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Display for BuildingDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_changes(f, "root", &self.roots)?;
+        write_changes(f, "block", &self.blocks)
+    }
+}
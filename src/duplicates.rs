@@ -0,0 +1,171 @@
+use std::ops::Range;
+
+use crate::bounds::Bounds;
+use crate::edit::Removal;
+use crate::id::BlockId;
+use crate::spatial::SpatialIndex;
+use crate::structs::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Which block of a group of duplicates [`Building::dedupe`] keeps.
+pub enum DedupePolicy {
+    /// The block with the lowest index.
+    KeepFirst,
+
+    /// The block with the highest index, e.g. the one a generator wrote last.
+    KeepLast,
+
+    /// The block with the most outgoing connections, then the lowest index.
+    KeepMostConnected,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A group of blocks sharing a position, found by [`Building::find_duplicates`].
+pub struct Duplicates {
+    /// Blocks of the group, sorted by handle.
+    pub blocks: Vec<BlockId>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Result of [`Building::dedupe`].
+pub struct Dedupe {
+    /// For every group of duplicates, the kept block (before removal) and the
+    /// removed ones.
+    pub kept: Vec<(BlockId, Vec<BlockId>)>,
+
+    /// Removal of the duplicates, mapping old to new block indices.
+    pub removal: Removal,
+
+    /// Blocks past the last handle, which could not be checked for
+    /// duplicates and were left as they are (see [`Building::validate`]).
+    pub unchecked: Range<usize>,
+}
+
+/// Smallest grid cell used to look for duplicates, so a zero tolerance does
+/// not shrink cells to nothing.
+const MIN_CELL_SIZE: f32 = 1e-3;
+
+impl Building {
+    /// Finds groups of blocks whose positions are within `tolerance` of each
+    /// other on every axis, regardless of their type.
+    ///
+    /// Every group is formed around its first block: it holds the blocks
+    /// within `tolerance` of that block that are not in an earlier group.
+    /// Closeness is not chained, so blocks spaced just under `tolerance`
+    /// apart along a line are not all merged. Groups are ordered by their
+    /// first block.
+    ///
+    /// Blocks past the last handle are not checked; [`Building::dedupe`]
+    /// reports them.
+    pub fn find_duplicates(&self, tolerance: f32) -> Vec<Duplicates> {
+        // Cells also grow with the farthest block, so cell coordinates stay
+        // well within `i32` and distant blocks do not all share one cell.
+        let reach = self.blocks
+            .iter()
+            .flat_map(|block| block.position)
+            .filter(|v| v.is_finite())
+            .fold(0.0, |reach: f32, v| reach.max(v.abs()));
+        let cell_size = (tolerance * 2.0).max(reach / (1 << 20) as f32).max(MIN_CELL_SIZE);
+        let index = SpatialIndex::new(self, cell_size);
+        let mut grouped = vec![false; self.blocks.len()];
+
+        let mut groups = Vec::new();
+        for (id, block) in self.iter_blocks() {
+            if grouped[id.index()] {
+                continue;
+            }
+            let around = Bounds {
+                min: block.position.map(|v| v - tolerance),
+                max: block.position.map(|v| v + tolerance),
+            };
+            let blocks: Vec<BlockId> = index.in_box(&around).into_iter().filter(|b| !grouped[b.index()]).collect();
+            for block in blocks.iter() {
+                grouped[block.index()] = true;
+            }
+            if blocks.len() > 1 {
+                groups.push(Duplicates { blocks });
+            }
+        }
+        groups
+    }
+
+    /// Keeps one block of every group found by [`Building::find_duplicates`],
+    /// chosen by `policy`, and removes the others.
+    ///
+    /// References to removed blocks are redirected to the kept block of
+    /// their group, and the outgoing connections of removed blocks are added
+    /// to it, so no wiring is lost. Connections that would become duplicate
+    /// or point at the block itself are dropped. Other references held by
+    /// removed blocks (`load`, metadata) are not carried over.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    /// use sw_structure_io::id::BlockId;
+    /// use sw_structure_io::duplicates::DedupePolicy;
+    ///
+    /// let mut building = Building::default();
+    /// building.roots.push(Root::default());
+    /// building.blocks.push(Block { connections: vec![2], ..Default::default() });
+    /// building.blocks.push(Block { position: [1.0, 0.0, 0.0], ..Default::default() });
+    /// building.blocks.push(Block { position: [1.0, 0.0, 0.0], ..Default::default() });
+    ///
+    /// let dedupe = building.dedupe(1e-3, DedupePolicy::KeepFirst);
+    /// assert_eq!(dedupe.kept, [(BlockId(1), vec![BlockId(2)])]);
+    /// assert_eq!(building.blocks.len(), 2);
+    /// assert_eq!(building.blocks[0].connections, vec![1]);
+    /// ```
+    pub fn dedupe(&mut self, tolerance: f32, policy: DedupePolicy) -> Dedupe {
+        // Kept block of every block, or `None` if it is kept itself.
+        let mut redirect: Vec<Option<BlockId>> = vec![None; self.blocks.len()];
+        let mut kept = Vec::new();
+
+        for Duplicates { blocks } in self.find_duplicates(tolerance) {
+            let keep = match policy {
+                DedupePolicy::KeepFirst => blocks[0],
+                DedupePolicy::KeepLast => blocks[blocks.len() - 1],
+                DedupePolicy::KeepMostConnected => *blocks
+                    .iter()
+                    .rev()
+                    .max_by_key(|&&id| self.blocks[id.index()].connections.len())
+                    .expect("groups are never empty"),
+            };
+            let removed: Vec<BlockId> = blocks.into_iter().filter(|&id| id != keep).collect();
+
+            for &id in removed.iter() {
+                redirect[id.index()] = Some(keep);
+                let connections = self.blocks[id.index()].connections.clone();
+                self.blocks[keep.index()].connections.extend(connections);
+            }
+            kept.push((keep, removed));
+        }
+
+        let mut is_kept = vec![false; self.blocks.len()];
+        for (keep, _) in kept.iter() {
+            is_kept[keep.index()] = true;
+        }
+        for (index, block) in self.blocks.iter_mut().enumerate() {
+            let mut touched = is_kept[index];
+            block.remap_references(|target| match redirect.get(target as usize).copied().flatten() {
+                Some(new) => {
+                    touched = true;
+                    (new.index() != index).then_some(new.0)
+                }
+                None => Some(target),
+            });
+            if !touched {
+                continue;
+            }
+            let mut seen = Vec::with_capacity(block.connections.len());
+            block.connections.retain(|&target| {
+                let new = !seen.contains(&target);
+                seen.push(target);
+                new
+            });
+        }
+
+        let unchecked = self.iter_blocks().count()..self.blocks.len();
+        let removal = self.remove_blocks(kept.iter().flat_map(|(_, removed)| removed.iter().copied()).collect::<Vec<_>>());
+        Dedupe { kept, removal, unchecked }
+    }
+}
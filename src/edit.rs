@@ -0,0 +1,505 @@
+use crate::id::{BlockId, RootId};
+use crate::structs::*;
+use crate::transform::Transform;
+
+impl Block {
+    /// Rewrites every block reference held by this block.
+    ///
+    /// `map` receives each referenced block index and returns its new index,
+    /// or `None` if the reference should be deleted. This covers
+    /// `connections`, `load`, `Metadata::fields` and the math block
+    /// `incoming_connections_order` (together with the paired slot).
+    ///
+    /// Returns the old index of every deleted reference, including math
+    /// order entries whose new index does not fit into their `u8`.
+    pub(crate) fn remap_references(&mut self, mut map: impl FnMut(u16) -> Option<u16>) -> Vec<u16> {
+        let mut deleted = Vec::new();
+
+        self.connections.retain_mut(|target| match map(*target) {
+            Some(new) => {
+                *target = new;
+                true
+            }
+            None => {
+                deleted.push(*target);
+                false
+            }
+        });
+
+        if let Some(target) = self.load {
+            self.load = map(target);
+            if self.load.is_none() {
+                deleted.push(target);
+            }
+        }
+
+        let Some(metadata) = &mut self.metadata else {
+            return deleted;
+        };
+
+        for items in metadata.fields.iter_mut() {
+            items.retain_mut(|target| match map(*target) {
+                Some(new) => {
+                    *target = new;
+                    true
+                }
+                None => {
+                    deleted.push(*target);
+                    false
+                }
+            });
+        }
+
+        if let TypeSettings::MathBlock { incoming_connections_order, slots, .. } = &mut metadata.type_settings {
+            let mut kept_order = Vec::with_capacity(incoming_connections_order.len());
+            let mut kept_slots = Vec::with_capacity(slots.len());
+            for (i, &target) in incoming_connections_order.iter().enumerate() {
+                // The order is stored as `u8`, so references past 255 can not be kept.
+                match map(target.into()).and_then(|new| u8::try_from(new).ok()) {
+                    Some(new) => {
+                        kept_order.push(new);
+                        if let Some(&slot) = slots.get(i) {
+                            kept_slots.push(slot);
+                        }
+                    }
+                    None => deleted.push(target.into()),
+                }
+            }
+            // Slots without a paired connection are kept as they are.
+            kept_slots.extend(slots.iter().skip(incoming_connections_order.len()));
+            *incoming_connections_order = kept_order;
+            *slots = kept_slots;
+        }
+
+        deleted
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Mapping of block indices from before an edit to after it.
+pub struct Remap {
+    map: Vec<Option<usize>>,
+}
+
+impl Remap {
+    /// New handle of the block that was at `old`, or `None` if it was removed.
+    pub fn get(&self, old: BlockId) -> Option<BlockId> {
+        self.map.get(old.index()).copied().flatten().and_then(|new| new.try_into().ok())
+    }
+
+    /// New index of every block, in old order.
+    pub fn as_slice(&self) -> &[Option<usize>] {
+        &self.map
+    }
+
+    /// Maps a raw block reference. References that were already out of
+    /// range are dropped too: after a shift they could alias a real block.
+    fn reference(&self, old: u16) -> Option<u16> {
+        self.map.get(old as usize).copied().flatten().and_then(|n| n.try_into().ok())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A reference that an edit deleted from a kept block.
+///
+/// The target was removed, was already out of range, or got an index past
+/// 255 that a math block's `incoming_connections_order` can not store.
+pub struct DeletedReference {
+    /// Block that held the reference, after the edit.
+    pub block: BlockId,
+
+    /// The referenced block, before the edit.
+    pub target: BlockId,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Result of an edit that renumbers blocks: removing, inserting or swapping
+/// them.
+pub struct Removal {
+    /// Mapping from old to new block indices.
+    pub remap: Remap,
+
+    /// References deleted from the kept blocks.
+    pub deleted: Vec<DeletedReference>,
+}
+
+impl Building {
+    /// Removes the given blocks.
+    ///
+    /// All block references (`connections`, `load`, `Metadata::fields` and
+    /// math block ordering) of the kept blocks are renumbered. References to
+    /// removed blocks are deleted and reported in the result, unless the
+    /// block holding them is still past the last handle. Handles out of range
+    /// are ignored.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    /// use sw_structure_io::id::BlockId;
+    ///
+    /// let mut building = Building::default();
+    /// building.roots.push(Root::default());
+    /// building.blocks.push(Block { connections: vec![1, 2], ..Default::default() });
+    /// building.blocks.push(Block::default());
+    /// building.blocks.push(Block::default());
+    ///
+    /// let removal = building.remove_blocks([BlockId(1)]);
+    /// assert_eq!(building.blocks[0].connections, vec![1]);
+    /// assert_eq!(removal.deleted.len(), 1);
+    /// ```
+    pub fn remove_blocks(&mut self, blocks: impl IntoIterator<Item = BlockId>) -> Removal {
+        let mut keep = vec![true; self.blocks.len()];
+        for block in blocks {
+            if let Some(k) = keep.get_mut(block.index()) {
+                *k = false;
+            }
+        }
+        self.compact_blocks(keep)
+    }
+
+    /// Keeps only the blocks for which `f` returns `true`.
+    ///
+    /// `f` receives the handle and the block. References are handled like in
+    /// [`Building::remove_blocks`]. Blocks past the last handle have none to
+    /// pass to `f`, and are kept.
+    pub fn retain_blocks(&mut self, mut f: impl FnMut(BlockId, &Block) -> bool) -> Removal {
+        let mut keep = vec![true; self.blocks.len()];
+        for (id, block) in self.iter_blocks() {
+            keep[id.index()] = f(id, block);
+        }
+        self.compact_blocks(keep)
+    }
+
+    fn compact_blocks(&mut self, keep: Vec<bool>) -> Removal {
+        let mut next = 0;
+        let remap = Remap {
+            map: keep.iter().map(|&k| k.then(|| { next += 1; next - 1 })).collect(),
+        };
+
+        self.blocks = std::mem::take(&mut self.blocks)
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(block, keep)| keep.then_some(block))
+            .collect();
+
+        let deleted = self.remap_all(|target| remap.reference(target));
+        Removal { remap, deleted }
+    }
+
+    /// Rewrites the references of every block with `map`, reporting the
+    /// deleted ones.
+    fn remap_all(&mut self, mut map: impl FnMut(u16) -> Option<u16>) -> Vec<DeletedReference> {
+        let mut deleted = Vec::new();
+        for (block, held) in self.blocks.iter_mut().enumerate() {
+            let dropped = held.remap_references(&mut map);
+            // Blocks still past the last handle have none to report against.
+            if let Ok(block) = BlockId::try_from(block) {
+                deleted.extend(dropped.into_iter().map(|target| DeletedReference { block, target: BlockId(target) }));
+            }
+        }
+        deleted
+    }
+
+    /// Inserts a block at `at`, shifting all following blocks.
+    ///
+    /// References of existing blocks are renumbered. References that were
+    /// out of range, or that a math block order can no longer store, are
+    /// deleted and reported in the result. References held by the inserted
+    /// block are taken as they are, i.e. they must already use the indices
+    /// after insertion.
+    ///
+    /// # Panics
+    /// Panics if `at` is past the end of `blocks`.
+    pub fn insert_block(&mut self, at: BlockId, block: Block) -> Removal {
+        let index = at.index();
+        assert!(index <= self.blocks.len(), "insertion index (is {index}) should be <= len (is {})", self.blocks.len());
+
+        let remap = Remap {
+            map: (0..self.blocks.len()).map(|i| Some(if i < index { i } else { i + 1 })).collect(),
+        };
+        let mut deleted = self.remap_all(|target| remap.reference(target));
+        self.blocks.insert(index, block);
+        for reference in deleted.iter_mut() {
+            reference.block = remap.get(reference.block).unwrap_or(reference.block);
+        }
+
+        Removal { remap, deleted }
+    }
+
+    /// Swaps two blocks, along with every reference to them.
+    ///
+    /// References that a math block order can no longer store are deleted
+    /// and reported in the result.
+    ///
+    /// # Panics
+    /// Panics if `a` or `b` are out of bounds.
+    pub fn swap_blocks(&mut self, a: BlockId, b: BlockId) -> Removal {
+        self.blocks.swap(a.index(), b.index());
+
+        let remap = Remap {
+            map: (0..self.blocks.len())
+                .map(|i| Some(if i == a.index() { b.index() } else if i == b.index() { a.index() } else { i }))
+                .collect(),
+        };
+        let (a, b) = (a.0, b.0);
+        let deleted = self.remap_all(|target| Some(match target {
+            t if t == a => b,
+            t if t == b => a,
+            t => t,
+        }));
+
+        Removal { remap, deleted }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What happens to the blocks of a removed root.
+pub enum RootBlocks {
+    /// Remove the blocks together with the root.
+    Delete,
+
+    /// Move the blocks to another root, given by its handle before removal.
+    MoveTo(RootId),
+}
+
+impl Building {
+    /// Removes a root, renumbering `Block::root` of all following roots.
+    ///
+    /// The blocks of the removed root are deleted or moved to another root,
+    /// depending on `blocks`. Deleting blocks is done with
+    /// [`Building::remove_blocks`], whose result is returned; when blocks are
+    /// moved, the returned removal is empty.
+    ///
+    /// # Panics
+    /// Panics if `root` or the root given in `RootBlocks::MoveTo` are out of
+    /// bounds, or if both are the same root.
+    pub fn remove_root(&mut self, root: RootId, blocks: RootBlocks) -> Removal {
+        let root = root.index();
+        assert!(root < self.roots.len(), "root index (is {root}) should be < len (is {})", self.roots.len());
+
+        let removal = match blocks {
+            RootBlocks::Delete => self.remove_blocks(
+                self.blocks_of(RootId(root as u16)).map(|(id, _)| id).collect::<Vec<BlockId>>()
+            ),
+            RootBlocks::MoveTo(target) => {
+                let target = target.index();
+                assert!(target < self.roots.len(), "root index (is {target}) should be < len (is {})", self.roots.len());
+                assert_ne!(root, target, "can not move blocks to the removed root");
+                self.move_blocks(root, target);
+                Removal {
+                    remap: Remap { map: (0..self.blocks.len()).map(Some).collect() },
+                    deleted: Vec::new(),
+                }
+            }
+        };
+
+        self.roots.remove(root);
+        for block in self.blocks.iter_mut() {
+            if block.root as usize > root {
+                block.root -= 1;
+            }
+        }
+
+        removal
+    }
+
+    /// Merges root `merged` into root `keep` and removes `merged`.
+    ///
+    /// The root transform of `keep` is kept. Returns the blocks
+    /// whose `load` now points into their own root (e.g. bearings that
+    /// connected the two roots), which the game does not expect; they are
+    /// left for the caller to resolve.
+    ///
+    /// # Panics
+    /// Panics if either root is out of bounds, or if both are the same root.
+    pub fn merge_roots(&mut self, keep: RootId, merged: RootId) -> Vec<BlockId> {
+        self.remove_root(merged, RootBlocks::MoveTo(keep));
+
+        self.iter_blocks()
+            .filter(|(_, block)| {
+                block.load_id()
+                    .and_then(|target| self.block(target))
+                    .is_some_and(|loaded| loaded.root == block.root)
+            })
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Moves the blocks of `root` selected by `f` into a new root.
+    ///
+    /// `f` receives the handle and the block, and is only called for blocks
+    /// of `root`. The new root is appended with the same transform as `root`,
+    /// and its handle is returned.
+    ///
+    /// # Panics
+    /// Panics if `root` is out of bounds, or if the building already has
+    /// `u16::MAX` roots.
+    pub fn split_root(&mut self, root: RootId, mut f: impl FnMut(BlockId, &Block) -> bool) -> RootId {
+        let transform = self.root(root)
+            .unwrap_or_else(|| panic!("root index (is {root}) should be < len (is {})", self.roots.len()))
+            .clone();
+        let new_root = self.push_root(transform);
+
+        for (index, block) in self.blocks.iter_mut().enumerate() {
+            if block.root == root.0 && f(BlockId(index as u16), block) {
+                block.root = new_root.0;
+            }
+        }
+
+        new_root
+    }
+
+    fn move_blocks(&mut self, from: usize, to: usize) {
+        for block in self.blocks.iter_mut() {
+            if block.root as usize == from {
+                block.root = to as u16;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Result of [`Building::merge`].
+pub struct Appended {
+    /// New handle of every root of the merged building, in its order.
+    pub roots: Vec<RootId>,
+
+    /// New handle of every block of the merged building, in its order.
+    pub blocks: Vec<BlockId>,
+
+    /// References that could not be offset and were deleted. Only math block
+    /// ordering can lose references, as it can not index past block 255.
+    pub dropped: usize,
+}
+
+impl Building {
+    /// Appends the roots and blocks of `other`, moved by `transform`.
+    ///
+    /// Every index reference of the appended blocks (`root`, `connections`,
+    /// `load`, `Metadata::fields` and math block ordering) is offset so it
+    /// keeps pointing at the same root or block. Positions and rotations of
+    /// the appended roots and blocks are transformed; the existing ones are
+    /// left as they are.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    /// use sw_structure_io::transform::Transform;
+    ///
+    /// let mut other = Building::default();
+    /// other.roots.push(Root::default());
+    /// other.blocks.push(Block { connections: vec![1], ..Default::default() });
+    /// other.blocks.push(Block::default());
+    ///
+    /// let mut building = other.clone();
+    /// building.merge(&other, &Transform::from_translation([0.0, 2.0, 0.0]));
+    /// assert_eq!(building.blocks[2].root, 1);
+    /// assert_eq!(building.blocks[2].connections, vec![3]);
+    /// assert_eq!(building.blocks[2].position, [0.0, 2.0, 0.0]);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if the merged building has more than `u16::MAX` roots or blocks.
+    pub fn merge(&mut self, other: &Building, transform: &Transform) -> Appended {
+        let root_offset = u16::try_from(self.roots.len()).expect("too many roots");
+        let block_offset = u16::try_from(self.blocks.len()).expect("too many blocks");
+        let mut appended = Appended::default();
+
+        for root in other.roots.iter() {
+            appended.roots.push(self.push_root(Root {
+                position: transform.apply_position(root.position),
+                rotation: transform.apply_rotation(root.rotation),
+            }));
+        }
+
+        for block in other.blocks.iter() {
+            let mut block = block.clone();
+            block.root = block.root.checked_add(root_offset).expect("too many roots");
+            appended.dropped += block.remap_references(|target| target.checked_add(block_offset)).len();
+            block.position = transform.apply_position(block.position);
+            block.rotation = transform.apply_rotation(block.rotation);
+            appended.blocks.push(self.push_block(block));
+        }
+
+        appended
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// A standalone building cut out of another one, see [`Building::extract_blocks`].
+pub struct Extraction {
+    /// The extracted roots and blocks.
+    pub building: Building,
+
+    /// Mapping from block indices of the source building to the extracted one.
+    pub remap: Remap,
+
+    /// For every root of the source building, its handle in the extracted
+    /// one, or `None` if none of its blocks were selected.
+    pub roots: Vec<Option<RootId>>,
+
+    /// References from selected blocks to blocks outside the selection,
+    /// which were dropped. `block` is an extracted block, `target` a source
+    /// block.
+    pub cut: Vec<DeletedReference>,
+}
+
+impl Building {
+    /// Copies the given blocks into a new building.
+    ///
+    /// Only roots holding selected blocks are kept, and both roots and
+    /// blocks are renumbered in their original order. References to blocks
+    /// outside the selection are dropped and reported in `cut`. Handles out
+    /// of range are ignored.
+    pub fn extract_blocks(&self, blocks: impl IntoIterator<Item = BlockId>) -> Extraction {
+        let mut keep = vec![false; self.blocks.len()];
+        for block in blocks {
+            if let Some(k) = keep.get_mut(block.index()) {
+                *k = true;
+            }
+        }
+
+        let mut building = self.clone();
+        let Removal { remap, deleted } = building.compact_blocks(keep);
+        let roots = building.compact_roots();
+
+        Extraction { building, remap, roots, cut: deleted }
+    }
+
+    /// Copies the blocks whose position lies inside the box from `min` to
+    /// `max` (inclusive) into a new building, like [`Building::extract_blocks`].
+    pub fn extract_region(&self, min: [f32; 3], max: [f32; 3]) -> Extraction {
+        self.extract_blocks(
+            self.iter_blocks()
+                .filter(|(_, block)| (0..3).all(|i| min[i] <= block.position[i] && block.position[i] <= max[i]))
+                .map(|(id, _)| id)
+                .collect::<Vec<BlockId>>()
+        )
+    }
+
+    /// Removes roots without blocks, returning the new handle of every root.
+    fn compact_roots(&mut self) -> Vec<Option<RootId>> {
+        let mut used = vec![false; self.roots.len()];
+        for block in self.blocks.iter() {
+            if let Some(u) = used.get_mut(block.root as usize) {
+                *u = true;
+            }
+        }
+
+        let mut next = 0;
+        let map: Vec<Option<RootId>> = used.iter().map(|&u| u.then(|| { next += 1; RootId(next - 1) })).collect();
+
+        self.roots = std::mem::take(&mut self.roots)
+            .into_iter()
+            .zip(used)
+            .filter_map(|(root, used)| used.then_some(root))
+            .collect();
+        for block in self.blocks.iter_mut() {
+            if let Some(Some(root)) = map.get(block.root as usize) {
+                block.root = root.0;
+            }
+        }
+
+        map
+    }
+}
@@ -0,0 +1,276 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt::Write;
+
+use crate::id::{self, BlockId};
+use crate::structs::*;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// The directed logic graph formed by `Block::connections`, built by
+/// [`Building::connection_graph`].
+///
+/// A connection goes from the block listing it to its target. The graph is a
+/// snapshot: it does not follow later edits of the building.
+///
+/// # Example
+/// ```rust
+/// use sw_structure_io::structs::*;
+/// use sw_structure_io::id::BlockId;
+///
+/// let mut building = Building::default();
+/// building.roots.push(Root::default());
+/// // A button (block 0) drives a gate (block 1) which drives a lamp (block 2).
+/// building.blocks.push(Block { connections: vec![1], ..Default::default() });
+/// building.blocks.push(Block { connections: vec![2], ..Default::default() });
+/// building.blocks.push(Block::default());
+///
+/// let graph = building.connection_graph();
+/// assert_eq!(graph.incoming(BlockId(1)).collect::<Vec<_>>(), [BlockId(0)]);
+/// assert_eq!(graph.reachable_from(BlockId(1)), [BlockId(1), BlockId(2)]);
+/// assert_eq!(graph.topological_order(), Some(vec![BlockId(0), BlockId(1), BlockId(2)]));
+/// ```
+pub struct ConnectionGraph {
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+}
+
+impl ConnectionGraph {
+    /// Number of blocks in the graph.
+    pub fn len(&self) -> usize {
+        self.outgoing.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outgoing.is_empty()
+    }
+
+    /// Blocks `block` is connected to, in connection order.
+    pub fn outgoing(&self, block: BlockId) -> impl Iterator<Item = BlockId> + '_ {
+        neighbors(&self.outgoing, block)
+    }
+
+    /// Blocks connected to `block`, sorted.
+    pub fn incoming(&self, block: BlockId) -> impl Iterator<Item = BlockId> + '_ {
+        neighbors(&self.incoming, block)
+    }
+
+    /// Groups of blocks that all reach each other through connections, each
+    /// sorted, ordered by their first block. Every block is in exactly one
+    /// group.
+    pub fn strongly_connected(&self) -> Vec<Vec<BlockId>> {
+        let mut components: Vec<Vec<BlockId>> = strongly_connected(&self.outgoing)
+            .into_iter()
+            .map(|component| {
+                let mut blocks: Vec<BlockId> = component.into_iter().map(id::handle).collect();
+                blocks.sort();
+                blocks
+            })
+            .collect();
+        components.sort();
+        components
+    }
+
+    /// Groups of blocks feeding back into themselves (latches, oscillators,
+    /// or wiring mistakes). A block connected to itself is a cycle of one.
+    pub fn cycles(&self) -> Vec<Vec<BlockId>> {
+        self.strongly_connected()
+            .into_iter()
+            .filter(|blocks| blocks.len() > 1 || self.outgoing(blocks[0]).any(|b| b == blocks[0]))
+            .collect()
+    }
+
+    /// Returns `true` if any signal feeds back into itself, see
+    /// [`ConnectionGraph::cycles`].
+    pub fn has_cycles(&self) -> bool {
+        !self.cycles().is_empty()
+    }
+
+    /// Every block ordered so that each comes after the blocks connected to
+    /// it, lowest index first among the blocks that are ready. `None` if the
+    /// graph has cycles.
+    pub fn topological_order(&self) -> Option<Vec<BlockId>> {
+        let mut waiting: Vec<usize> = self.incoming.iter().map(Vec::len).collect();
+        let mut ready: BinaryHeap<Reverse<usize>> =
+            (0..self.len()).filter(|&i| waiting[i] == 0).map(Reverse).collect();
+
+        let mut order = Vec::with_capacity(self.len());
+        while let Some(Reverse(block)) = ready.pop() {
+            order.push(id::handle(block));
+            for &next in self.outgoing[block].iter() {
+                waiting[next] -= 1;
+                if waiting[next] == 0 {
+                    ready.push(Reverse(next));
+                }
+            }
+        }
+        (order.len() == self.len()).then_some(order)
+    }
+
+    /// `from` and every block its signal reaches, sorted.
+    pub fn reachable_from(&self, from: BlockId) -> Vec<BlockId> {
+        let mut reached = vec![false; self.len()];
+        let mut stack = Vec::new();
+        if from.index() < self.len() {
+            reached[from.index()] = true;
+            stack.push(from.index());
+        }
+        while let Some(block) = stack.pop() {
+            for &next in self.outgoing[block].iter() {
+                if !reached[next] {
+                    reached[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+        (0..self.len()).filter(|&i| reached[i]).map(id::handle).collect()
+    }
+
+    /// Blocks with an output that is connected to nothing, sorted.
+    ///
+    /// The library does not know which block types have outputs (a lamp has
+    /// none), so `has_output` decides. With `|_| true`, every block without
+    /// outgoing connections is returned.
+    pub fn unconnected_outputs(&self, mut has_output: impl FnMut(BlockId) -> bool) -> Vec<BlockId> {
+        (0..self.len())
+            .map(id::handle::<BlockId>)
+            .filter(|&block| self.outgoing[block.index()].is_empty() && has_output(block))
+            .collect()
+    }
+
+    /// Renders the graph in Graphviz DOT format, with one cluster per root.
+    ///
+    /// Blocks are labelled with their index and name, or their type when
+    /// unnamed. `building` should be the one the graph was built from.
+    pub fn to_dot(&self, building: &Building) -> String {
+        // Blocks of roots that do not exist are still drawn, outside clusters.
+        let mut clusters = vec![Vec::new(); building.iter_roots().count()];
+        let mut outside = Vec::new();
+        for (id, block) in building.iter_blocks().take(self.len()) {
+            match clusters.get_mut(block.root as usize) {
+                Some(cluster) => cluster.push((id, block)),
+                None => outside.push((id, block)),
+            }
+        }
+
+        let mut dot = String::from("digraph connections {\n");
+        for (root, blocks) in clusters.iter().enumerate() {
+            let _ = writeln!(dot, "    subgraph cluster_root_{root} {{\n        label=\"root {root}\";");
+            for &(id, block) in blocks {
+                let _ = writeln!(dot, "        {id} [label=\"{id}: {}\"];", label(block));
+            }
+            dot.push_str("    }\n");
+        }
+        for (id, block) in outside {
+            let _ = writeln!(dot, "    {id} [label=\"{id}: {}\"];", label(block));
+        }
+        for (from, targets) in self.outgoing.iter().enumerate() {
+            for to in targets {
+                let _ = writeln!(dot, "    {from} -> {to};");
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn neighbors(adjacency: &[Vec<usize>], block: BlockId) -> impl Iterator<Item = BlockId> + '_ {
+    adjacency.get(block.index()).into_iter().flatten().map(|&i| id::handle(i))
+}
+
+/// Label of a block: its name, or its type when unnamed.
+fn label(block: &Block) -> String {
+    if block.name.is_empty() {
+        format!("type {}", block.id)
+    } else {
+        escape(&block.name)
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Strongly connected components of a graph given as successor lists, in
+/// reverse topological order (components nothing leaves come first).
+///
+/// Tarjan's algorithm, without recursion so long chains cannot overflow the
+/// stack.
+pub(crate) fn strongly_connected(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let len = successors.len();
+    let mut order = vec![usize::MAX; len];
+    let mut low = vec![0; len];
+    let mut on_stack = vec![false; len];
+    let mut stack = Vec::new();
+    let mut next_order = 0;
+    let mut components = Vec::new();
+
+    for start in 0..len {
+        if order[start] != usize::MAX {
+            continue;
+        }
+        // Nodes being visited, with the position of their next successor.
+        let mut calls = vec![(start, 0)];
+        order[start] = next_order;
+        low[start] = next_order;
+        next_order += 1;
+        stack.push(start);
+        on_stack[start] = true;
+
+        while let Some((node, child)) = calls.last_mut() {
+            let node = *node;
+            if let Some(&next) = successors[node].get(*child) {
+                *child += 1;
+                if order[next] == usize::MAX {
+                    order[next] = next_order;
+                    low[next] = next_order;
+                    next_order += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    calls.push((next, 0));
+                } else if on_stack[next] {
+                    low[node] = low[node].min(order[next]);
+                }
+                continue;
+            }
+
+            calls.pop();
+            if let Some(&(caller, _)) = calls.last() {
+                low[caller] = low[caller].min(low[node]);
+            }
+            if low[node] == order[node] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+impl Building {
+    /// Builds the graph of `Block::connections`.
+    ///
+    /// Connections to blocks that do not exist are left out (see
+    /// [`Building::validate`]); repeated connections are kept once. Blocks
+    /// past the last handle are left out too.
+    pub fn connection_graph(&self) -> ConnectionGraph {
+        let len = self.iter_blocks().count();
+        let mut graph = ConnectionGraph { outgoing: vec![Vec::new(); len], incoming: vec![Vec::new(); len] };
+        for (from, block) in self.iter_blocks() {
+            let from = from.index();
+            for to in block.connections.iter().map(|&to| to as usize).filter(|&to| to < len) {
+                if !graph.outgoing[from].contains(&to) {
+                    graph.outgoing[from].push(to);
+                    graph.incoming[to].push(from);
+                }
+            }
+        }
+        graph
+    }
+}
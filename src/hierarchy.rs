@@ -0,0 +1,215 @@
+use std::collections::VecDeque;
+
+use crate::graph;
+use crate::id::{BlockId, RootId};
+use crate::structs::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A `load` link between two roots: `block` (a bearing, shock absorber…) of
+/// the `parent` root carries `load`, a block of the `child` root.
+pub struct Joint {
+    pub block: BlockId,
+    pub load: BlockId,
+    pub parent: RootId,
+    pub child: RootId,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// The graph of roots linked by `Block::load`, built by
+/// [`Building::root_graph`].
+///
+/// In a well-formed building the graph is a tree: the base root carries the
+/// others through joints, and every other root hangs from exactly one parent.
+/// The graph still represents buildings that are not trees, and reports their
+/// cycles and disconnected roots.
+///
+/// # Example
+/// ```rust
+/// use sw_structure_io::structs::*;
+/// use sw_structure_io::id::RootId;
+///
+/// let mut building = Building::default();
+/// for _ in 0..3 {
+///     building.roots.push(Root::default());
+/// }
+/// // Root 0 carries root 1 through block 0, root 1 carries root 2 through block 1.
+/// building.blocks.push(Block { root: 0, load: Some(1), ..Default::default() });
+/// building.blocks.push(Block { root: 1, load: Some(2), ..Default::default() });
+/// building.blocks.push(Block { root: 2, ..Default::default() });
+///
+/// let graph = building.root_graph();
+/// assert_eq!(graph.base(), Some(RootId(0)));
+/// assert_eq!(graph.traverse(), [RootId(0), RootId(1), RootId(2)]);
+/// assert!(graph.is_tree());
+/// ```
+pub struct RootGraph {
+    joints: Vec<Joint>,
+    base: Option<RootId>,
+    /// Indices into `joints`, per root.
+    parents: Vec<Vec<usize>>,
+    children: Vec<Vec<usize>>,
+}
+
+impl RootGraph {
+    /// Every joint, ordered by carrying block.
+    pub fn joints(&self) -> &[Joint] {
+        &self.joints
+    }
+
+    /// The root carrying the others: the first root that no other root
+    /// carries. `None` if there are no roots, or if every root is carried
+    /// (the roots form a cycle).
+    pub fn base(&self) -> Option<RootId> {
+        self.base
+    }
+
+    /// Joints carrying `root`; a tree has exactly one for every root but the
+    /// base.
+    pub fn parents(&self, root: RootId) -> impl Iterator<Item = &Joint> {
+        self.joints_at(&self.parents, root)
+    }
+
+    /// Joints carried by `root`.
+    pub fn children(&self, root: RootId) -> impl Iterator<Item = &Joint> {
+        self.joints_at(&self.children, root)
+    }
+
+    /// Roots reachable from the base through joints, breadth first, starting
+    /// with the base. Empty if there is no base.
+    pub fn traverse(&self) -> Vec<RootId> {
+        self.base.map(|base| self.descendants(base)).unwrap_or_default()
+    }
+
+    /// `root` and every root it carries, directly or not, breadth first.
+    ///
+    /// This is the subassembly that moves with `root`.
+    pub fn descendants(&self, root: RootId) -> Vec<RootId> {
+        let mut visited = vec![false; self.children.len()];
+        let mut order = Vec::new();
+        let mut queue = VecDeque::new();
+        if root.index() < visited.len() {
+            visited[root.index()] = true;
+            queue.push_back(root);
+        }
+        while let Some(root) = queue.pop_front() {
+            order.push(root);
+            for joint in self.children(root) {
+                if !visited[joint.child.index()] {
+                    visited[joint.child.index()] = true;
+                    queue.push_back(joint.child);
+                }
+            }
+        }
+        order
+    }
+
+    /// Groups of roots carrying each other in a loop, each sorted, ordered by
+    /// their first root. A root carrying itself is a cycle of one.
+    pub fn cycles(&self) -> Vec<Vec<RootId>> {
+        let mut cycles: Vec<Vec<RootId>> = self
+            .strongly_connected()
+            .into_iter()
+            .filter(|roots| roots.len() > 1 || self.children(roots[0]).any(|joint| joint.child == roots[0]))
+            .map(|mut roots| {
+                roots.sort();
+                roots
+            })
+            .collect();
+        cycles.sort();
+        cycles
+    }
+
+    /// Roots not linked to the base, directly or through other roots, in
+    /// either direction. Empty if there is no base.
+    pub fn disconnected(&self) -> Vec<RootId> {
+        let Some(base) = self.base else {
+            return Vec::new();
+        };
+        let mut linked = vec![false; self.children.len()];
+        linked[base.index()] = true;
+        let mut stack = vec![base];
+        while let Some(root) = stack.pop() {
+            for joint in self.children(root).chain(self.parents(root)) {
+                for next in [joint.parent, joint.child] {
+                    if !linked[next.index()] {
+                        linked[next.index()] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        (0..linked.len()).filter(|&i| !linked[i]).map(|i| RootId(i as u16)).collect()
+    }
+
+    /// Returns `true` if every root but the base is carried by exactly one
+    /// joint, and all of them are reachable from the base.
+    pub fn is_tree(&self) -> bool {
+        let Some(base) = self.base else {
+            return self.children.is_empty();
+        };
+        (0..self.parents.len()).all(|i| self.parents[i].len() == usize::from(i != base.index()))
+            && self.traverse().len() == self.children.len()
+    }
+
+    fn joints_at<'a>(&'a self, at: &'a [Vec<usize>], root: RootId) -> impl Iterator<Item = &'a Joint> {
+        at.get(root.index()).into_iter().flatten().map(|&joint| &self.joints[joint])
+    }
+
+    fn strongly_connected(&self) -> Vec<Vec<RootId>> {
+        let successors: Vec<Vec<usize>> = self
+            .children
+            .iter()
+            .map(|joints| joints.iter().map(|&joint| self.joints[joint].child.index()).collect())
+            .collect();
+        graph::strongly_connected(&successors)
+            .into_iter()
+            .map(|component| component.into_iter().map(|i| RootId(i as u16)).collect())
+            .collect()
+    }
+}
+
+impl Building {
+    /// Builds the graph of roots linked by `Block::load`.
+    ///
+    /// Links to blocks that do not exist, and blocks of roots that do not
+    /// exist, are ignored (see [`Building::validate`]).
+    pub fn root_graph(&self) -> RootGraph {
+        let mut graph = RootGraph {
+            parents: vec![Vec::new(); self.roots.len()],
+            children: vec![Vec::new(); self.roots.len()],
+            ..Default::default()
+        };
+
+        for (block, carrier) in self.iter_blocks() {
+            let Some(load) = carrier.load_id() else {
+                continue;
+            };
+            let Some(loaded) = self.block(load) else {
+                continue;
+            };
+            let (parent, child) = (carrier.root_id(), loaded.root_id());
+            if parent.index() >= self.roots.len() || child.index() >= self.roots.len() {
+                continue;
+            }
+            graph.parents[child.index()].push(graph.joints.len());
+            graph.children[parent.index()].push(graph.joints.len());
+            graph.joints.push(Joint { block, load, parent, child });
+        }
+
+        graph.base = (0..self.roots.len()).find(|&i| graph.parents[i].is_empty()).map(|i| RootId(i as u16));
+        graph
+    }
+
+    /// Blocks of `root` and of every root it carries, sorted: the whole
+    /// subassembly hanging from `root`.
+    pub fn subassembly(&self, root: RootId) -> Vec<BlockId> {
+        let mut roots = vec![false; self.roots.len()];
+        for root in self.root_graph().descendants(root) {
+            roots[root.index()] = true;
+        }
+        self.iter_blocks()
+            .filter(|(_, block)| roots.get(block.root as usize).copied().unwrap_or(false))
+            .map(|(id, _)| id)
+            .collect()
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::structs::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// Handle to a block of a building: its index in `Building::blocks`.
+///
+/// Block references in the raw structs (`connections`, `load`,
+/// `Metadata::fields`) are plain `u16` indices; `BlockId` converts from and
+/// into them, so code can move to handles one field at a time.
+pub struct BlockId(pub u16);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// Handle to a root of a building: its index in `Building::roots`.
+///
+/// Converts from and into the raw `Block::root` index.
+pub struct RootId(pub u16);
+
+macro_rules! impl_id {
+    ($($t:ident),*) => {
+        $(
+            impl $t {
+                /// Index into the building's vector.
+                pub const fn index(self) -> usize {
+                    self.0 as usize
+                }
+            }
+
+            impl From<u16> for $t {
+                fn from(index: u16) -> Self {
+                    Self(index)
+                }
+            }
+
+            impl From<$t> for u16 {
+                fn from(id: $t) -> Self {
+                    id.0
+                }
+            }
+
+            impl From<$t> for usize {
+                fn from(id: $t) -> Self {
+                    id.index()
+                }
+            }
+
+            impl TryFrom<usize> for $t {
+                type Error = std::num::TryFromIntError;
+                fn try_from(index: usize) -> Result<Self, Self::Error> {
+                    u16::try_from(index).map(Self)
+                }
+            }
+
+            impl Display for $t {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write!(f, "{}", self.0)
+                }
+            }
+        )*
+    };
+}
+
+impl_id!(BlockId, RootId);
+
+/// Converts a vector index into a handle. A handle addresses at most
+/// `u16::MAX + 1` roots or blocks, the formats can not index more.
+pub(crate) fn handle<T>(index: usize) -> T
+where
+    T: TryFrom<usize>,
+    T::Error: fmt::Debug,
+{
+    T::try_from(index).expect("index does not fit into a handle")
+}
+
+/// Pairs items with their handles, stopping at the first index a handle can
+/// not address. Buildings loaded from JSON are not limited in size, and
+/// report the excess through [`Building::validate`] instead.
+fn with_handles<T, I>(items: &[I]) -> impl Iterator<Item = (T, &I)>
+where
+    T: TryFrom<usize>,
+{
+    items.iter().enumerate().map_while(|(i, item)| T::try_from(i).ok().map(|id| (id, item)))
+}
+
+impl Block {
+    /// Root this block belongs to.
+    pub fn root_id(&self) -> RootId {
+        RootId(self.root)
+    }
+
+    /// Blocks this block is connected to.
+    pub fn connection_ids(&self) -> impl Iterator<Item = BlockId> + '_ {
+        self.connections.iter().map(|&i| BlockId(i))
+    }
+
+    /// Block from another root that is mechanically attached to this one.
+    pub fn load_id(&self) -> Option<BlockId> {
+        self.load.map(BlockId)
+    }
+
+    /// Blocks placed in a metadata field slot, see [`Metadata::field_ids`].
+    pub fn field_ids(&self, field: usize) -> impl Iterator<Item = BlockId> + '_ {
+        self.metadata.iter().flat_map(move |metadata| metadata.field_ids(field))
+    }
+
+    /// Ordered inputs of a math block, see [`TypeSettings::incoming_ids`].
+    pub fn incoming_ids(&self) -> impl Iterator<Item = (BlockId, Option<u8>)> + '_ {
+        self.metadata.iter().flat_map(|metadata| metadata.type_settings.incoming_ids())
+    }
+}
+
+impl Metadata {
+    /// Blocks placed in the slot `field`; empty if there is no such field.
+    pub fn field_ids(&self, field: usize) -> impl Iterator<Item = BlockId> + '_ {
+        self.fields.get(field).into_iter().flatten().map(|&i| BlockId(i))
+    }
+}
+
+impl TypeSettings {
+    /// Inputs of a math block in `incoming_connections_order`, each with its
+    /// paired slot (`None` when `slots` is shorter). Empty for other types.
+    ///
+    /// The raw order stores `u8` indices, so it can only reference the
+    /// first 256 blocks.
+    pub fn incoming_ids(&self) -> impl Iterator<Item = (BlockId, Option<u8>)> + '_ {
+        let (order, slots): (&[u8], &[u8]) = match self {
+            TypeSettings::MathBlock { incoming_connections_order, slots, .. } => (incoming_connections_order, slots),
+            TypeSettings::None => (&[], &[]),
+        };
+        order.iter().enumerate().map(|(i, &block)| (BlockId(block.into()), slots.get(i).copied()))
+    }
+}
+
+impl Building {
+    /// Returns the block with the given handle.
+    pub fn block(&self, id: BlockId) -> Option<&Block> {
+        self.blocks.get(id.index())
+    }
+
+    /// Returns the block with the given handle, mutably.
+    pub fn block_mut(&mut self, id: BlockId) -> Option<&mut Block> {
+        self.blocks.get_mut(id.index())
+    }
+
+    /// Returns the root with the given handle.
+    pub fn root(&self, id: RootId) -> Option<&Root> {
+        self.roots.get(id.index())
+    }
+
+    /// Returns the root with the given handle, mutably.
+    pub fn root_mut(&mut self, id: RootId) -> Option<&mut Root> {
+        self.roots.get_mut(id.index())
+    }
+
+    /// Returns the root a block belongs to.
+    pub fn root_of(&self, id: BlockId) -> Option<&Root> {
+        self.block(id).and_then(|block| self.root(block.root_id()))
+    }
+
+    /// Iterates over all blocks with their handles.
+    ///
+    /// Blocks past the last addressable handle (`u16::MAX`) are skipped.
+    pub fn iter_blocks(&self) -> impl Iterator<Item = (BlockId, &Block)> {
+        with_handles(&self.blocks)
+    }
+
+    /// Iterates over all roots with their handles.
+    ///
+    /// Roots past the last addressable handle (`u16::MAX`) are skipped.
+    pub fn iter_roots(&self) -> impl Iterator<Item = (RootId, &Root)> {
+        with_handles(&self.roots)
+    }
+
+    /// Iterates over the blocks of a root with their handles.
+    pub fn blocks_of(&self, root: RootId) -> impl Iterator<Item = (BlockId, &Block)> {
+        self.iter_blocks().filter(move |(_, block)| block.root == root.0)
+    }
+
+    /// Appends a root and returns its handle.
+    ///
+    /// # Panics
+    /// Panics if the building already has `u16::MAX + 1` roots (65536), the
+    /// most a handle can address.
+    pub fn push_root(&mut self, root: Root) -> RootId {
+        let root_id = handle(self.roots.len());
+        self.roots.push(root);
+        root_id
+    }
+
+    /// Appends a block and returns its handle.
+    ///
+    /// # Panics
+    /// Panics if the building already has `u16::MAX + 1` blocks (65536), the
+    /// most a handle can address.
+    pub fn push_block(&mut self, block: Block) -> BlockId {
+        let block_id = handle(self.blocks.len());
+        self.blocks.push(block);
+        block_id
+    }
+}
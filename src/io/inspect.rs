@@ -0,0 +1,280 @@
+use std::fmt::{self, Debug, Display};
+use std::io::{self, Read};
+use std::ops::Range;
+
+use log::trace;
+
+use crate::io::utils::{LE, NumericBytes, ReadUtilsExt};
+use crate::io::version;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Maximum number of raw bytes printed per field in the annotated dump.
+const MAX_PRINTED_BYTES: usize = 16;
+
+#[derive(Clone, Debug)]
+/// A single decoded field of a building file.
+pub struct Field {
+    /// Byte range of the field in the file (including the version byte).
+    pub range: Range<usize>,
+
+    /// Raw bytes of the field.
+    pub raw: Vec<u8>,
+
+    /// Dotted path of the field, e.g. `blocks[3].flags`.
+    pub path: String,
+
+    /// Interpreted value, formatted for humans.
+    pub value: String,
+
+    /// Named bits, for fields that are bit sets (e.g. block flags).
+    pub bits: Vec<(&'static str, bool)>,
+}
+
+#[derive(Debug)]
+/// Result of inspecting a building file.
+///
+/// Inspection never stops at the first decoding error: all fields decoded up
+/// to that point are kept, which is usually exactly what is needed when
+/// figuring out an unknown layout.
+pub struct Inspection {
+    /// Format version, if at least one byte could be read.
+    pub version: Option<u8>,
+
+    /// All decoded fields in file order.
+    pub fields: Vec<Field>,
+
+    /// Error that stopped decoding, if any.
+    pub error: Option<Box<dyn std::error::Error>>,
+
+    /// Number of bytes left in the stream after decoding finished.
+    pub trailing: usize,
+}
+
+/// A reader the version decoders report every field they read to.
+///
+/// Buildings are decoded through [`Untracked`], which only traces the
+/// fields, and inspected through [`Inspector`], which records their byte
+/// spans. Both run the same decoder, so the inspection can not drift from
+/// what is actually read.
+pub(crate) trait FieldReader: Read {
+    /// Enters a nested scope, e.g. `blocks[3]`.
+    fn push_scope(&mut self, scope: impl Display);
+
+    fn pop_scope(&mut self);
+
+    /// Reports every byte read since the last report as the field `name`,
+    /// with a named breakdown of its bits.
+    fn record_bits(&mut self, name: impl Display, value: &impl Debug, bits: &[(&'static str, bool)]);
+
+    /// Reports every byte read since the last report as the field `name`.
+    fn record(&mut self, name: impl Display, value: &impl Debug) {
+        self.record_bits(name, value, &[]);
+    }
+
+    /// Reads a length-prefixed vector, reported as two fields: `name.len`
+    /// and `name` (left out when empty).
+    fn read_vec_field<L, T>(&mut self, name: impl Display) -> Result<Vec<T>>
+    where
+        L: NumericBytes<LE> + TryInto<usize> + Debug,
+        L::Error: std::error::Error + 'static,
+        T: NumericBytes<LE> + Debug,
+    {
+        let len = self.read_num::<L, LE>()?;
+        self.record(format_args!("{name}.len"), &len);
+
+        let mut vec = Vec::new();
+        for _ in 0..len.try_into()? {
+            vec.push(self.read_num::<T, LE>()?);
+        }
+        if !vec.is_empty() {
+            self.record(name, &vec);
+        }
+        Ok(vec)
+    }
+}
+
+impl<F: FieldReader + ?Sized> FieldReader for &mut F {
+    fn push_scope(&mut self, scope: impl Display) {
+        (**self).push_scope(scope);
+    }
+
+    fn pop_scope(&mut self) {
+        (**self).pop_scope();
+    }
+
+    fn record_bits(&mut self, name: impl Display, value: &impl Debug, bits: &[(&'static str, bool)]) {
+        (**self).record_bits(name, value, bits);
+    }
+}
+
+/// Reader that decodes without recording anything, tracing the fields.
+pub(crate) struct Untracked<R>(pub(crate) R);
+
+impl<R: Read> Read for Untracked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R: Read> FieldReader for Untracked<R> {
+    fn push_scope(&mut self, _scope: impl Display) {}
+
+    fn pop_scope(&mut self) {}
+
+    fn record_bits(&mut self, name: impl Display, value: &impl Debug, _bits: &[(&'static str, bool)]) {
+        trace!("{name}: {value:?}");
+    }
+}
+
+/// Reader wrapper that records byte spans as they are read.
+///
+/// Bytes read through the inspector are collected until the next reported
+/// field, which turns them into a [`Field`] named after the current scope.
+pub(crate) struct Inspector<R> {
+    inner: R,
+    offset: usize,
+    start: usize,
+    pending: Vec<u8>,
+    scopes: Vec<String>,
+    fields: Vec<Field>,
+}
+
+impl<R: Read> Read for Inspector<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pending.extend_from_slice(&buf[..n]);
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+impl<R: Read> FieldReader for Inspector<R> {
+    fn push_scope(&mut self, scope: impl Display) {
+        self.scopes.push(scope.to_string());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn record_bits(&mut self, name: impl Display, value: &impl Debug, bits: &[(&'static str, bool)]) {
+        let mut path = self.scopes.join(".");
+        if !path.is_empty() {
+            path.push('.');
+        }
+        path.push_str(&name.to_string());
+
+        self.fields.push(Field {
+            range: self.start..self.offset,
+            raw: std::mem::take(&mut self.pending),
+            path,
+            value: format!("{value:?}"),
+            bits: bits.to_vec(),
+        });
+        self.start = self.offset;
+    }
+}
+
+impl<R: Read> Inspector<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            offset: 0,
+            start: 0,
+            pending: Vec::new(),
+            scopes: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+
+    fn finish(mut self, version: Option<u8>, error: Option<Box<dyn std::error::Error>>) -> Inspection {
+        if !self.pending.is_empty() {
+            self.record("<incomplete>", &());
+        }
+
+        let mut rest = Vec::new();
+        let trailing = self.inner.read_to_end(&mut rest).unwrap_or(0);
+
+        Inspection { version, fields: self.fields, error, trailing }
+    }
+}
+
+/// Decodes a building file, recording every field with its byte range.
+///
+/// This is a diagnostic tool for reverse-engineering the format: the result
+/// lists every field with its raw bytes and interpreted value, and can be
+/// printed as an annotated hex dump with `Display`.
+///
+/// # Example
+/// ```rust
+/// use sw_structure_io::structs::*;
+/// use sw_structure_io::io::{WriteBuilding, inspect_building};
+///
+/// let mut buffer = vec![];
+/// buffer.write_building(&Building::default(), 0).unwrap();
+///
+/// let inspection = inspect_building(&buffer[..]);
+/// assert!(inspection.error.is_none());
+/// println!("{inspection}");
+/// ```
+pub fn inspect_building<R: Read>(r: R) -> Inspection {
+    let mut inspector = Inspector::new(r);
+
+    let version = match inspector.read_num::<u8, LE>() {
+        Ok(version) => version,
+        Err(e) => return inspector.finish(None, Some(Box::new(e))),
+    };
+    inspector.record("version", &version);
+
+    let result = match version {
+        0 => version::v0::read_building(&mut inspector).map(|_| ()),
+        _ => Err(Box::new(super::Error::UnsuportedVersion { version }) as Box<dyn std::error::Error>),
+    };
+
+    inspector.finish(Some(version), result.err())
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut hex: Vec<String> = self.raw
+            .iter()
+            .take(MAX_PRINTED_BYTES)
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        if self.raw.len() > MAX_PRINTED_BYTES {
+            hex.push("..".to_string());
+        }
+
+        write!(
+            f,
+            "{:08x}..{:08x}  {:<50} {} = {}",
+            self.range.start,
+            self.range.end,
+            hex.join(" "),
+            self.path,
+            self.value
+        )?;
+
+        for (bit, (name, set)) in self.bits.iter().enumerate() {
+            write!(f, "\n{:20}  bit {bit}: {name:<30} {}", "", *set as u8)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Inspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for field in self.fields.iter() {
+            writeln!(f, "{field}")?;
+        }
+        if let Some(e) = &self.error {
+            writeln!(f, "Decoding stopped: {e}")?;
+        }
+        if self.trailing > 0 {
+            writeln!(f, "{} trailing bytes not decoded", self.trailing)?;
+        }
+        Ok(())
+    }
+}
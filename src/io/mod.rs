@@ -4,13 +4,18 @@ use std::{
 
 mod version;
 mod utils;
+mod inspect;
+mod quantize;
+
+pub use inspect::{Field, Inspection, inspect_building};
+pub use quantize::{BlockQuantization, QuantizationPreview, preview_quantization};
 
 use crate::{io::utils::{LE, ReadUtilsExt, WriteUtilsExt}, structs::Building};
 use log::{debug, error, info, trace, warn};
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
-static NOT_INTERACTABLE: LazyLock<HashSet<u8>> = LazyLock::new(||{[
+pub(crate) static NOT_INTERACTABLE: LazyLock<HashSet<u8>> = LazyLock::new(||{[
     00, 01, 28, 33, 34, 35, 36, 37, 38,
     59, 62, 63, 64, 65, 66, 67, 68, 69,
     70, 71, 72, 73, 74, 75, 86, 87, 88
@@ -113,7 +118,7 @@ pub trait ReadBuilding: Read {
         info!("Selected reader for building version {version}");
 
         match version {
-            0 => version::v0::read_building(self),
+            0 => version::v0::read_building(inspect::Untracked(self)),
             _ => return Err(Box::new(Error::UnsuportedVersion { version }))
         }
     }
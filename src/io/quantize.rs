@@ -0,0 +1,74 @@
+use crate::io::Error::UnsuportedVersion;
+use crate::io::utils::{pack_rotation, unpack_rotation};
+use crate::math;
+use crate::structs::*;
+
+use super::Result;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// Precision lost by a single block when it is written.
+pub struct BlockQuantization {
+    /// Angle in degrees between the rotation and the rotation read back.
+    pub rotation: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Precision a building loses when written at a given version, produced by
+/// [`preview_quantization`].
+pub struct QuantizationPreview {
+    /// Error of every block, in block order.
+    pub blocks: Vec<BlockQuantization>,
+}
+
+impl QuantizationPreview {
+    /// Largest rotation error of any block, in degrees.
+    pub fn max_rotation_error(&self) -> f32 {
+        self.blocks.iter().map(|b| b.rotation).fold(0.0, f32::max)
+    }
+
+    /// Indices of blocks whose rotation error exceeds `tolerance` degrees.
+    pub fn blocks_over(&self, tolerance: f32) -> impl Iterator<Item = usize> + '_ {
+        self.blocks.iter().enumerate().filter(move |(_, b)| b.rotation > tolerance).map(|(i, _)| i)
+    }
+}
+
+/// Computes how much precision every block loses when `building` is written
+/// at `version`, without writing it.
+///
+/// Version 0 stores positions as `f32`, so they are kept exactly: only
+/// rotations, stored as 16-bit angles, lose precision. Only versions
+/// [`WriteBuilding`](super::WriteBuilding) supports can be previewed.
+///
+/// # Example
+/// ```rust
+/// use sw_structure_io::structs::*;
+/// use sw_structure_io::io::preview_quantization;
+///
+/// let mut building = Building::default();
+/// building.roots.push(Root::default());
+/// building.blocks.push(Block { position: [5000.0, 0.3, 0.0], rotation: [12.345, 0.0, 0.0], ..Default::default() });
+///
+/// let preview = preview_quantization(&building, 0).unwrap();
+/// assert!(preview.max_rotation_error() < 0.01);
+/// assert!(preview_quantization(&building, 6).is_err());
+/// ```
+///
+/// # Errors
+/// Returns an error if the version can not be written.
+pub fn preview_quantization(building: &Building, version: u8) -> Result<QuantizationPreview> {
+    match version {
+        0 => {}
+        _ => return Err(Box::new(UnsuportedVersion { version })),
+    }
+
+    let blocks = building.blocks
+        .iter()
+        .map(|block| {
+            let stored = unpack_rotation(pack_rotation(block.rotation));
+            let rotation = math::angle_between(math::from_euler(block.rotation), math::from_euler(stored));
+            BlockQuantization { rotation }
+        })
+        .collect();
+
+    Ok(QuantizationPreview { blocks })
+}
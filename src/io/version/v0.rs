@@ -6,10 +6,23 @@ use std::ops::DerefMut;
 use std::{io::Write, ops::Deref};
 use crate::io::Error::*;
 use crate::io::utils::*;
+use crate::io::inspect::FieldReader;
 use log::{debug, error, info, warn, trace};
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+/// Names of the bits in the block flag byte, in bit order.
+pub(crate) const BLOCK_FLAGS: [&str; 8] = [
+    "has_name",
+    "has_connections",
+    "no_metadata",
+    "no_color",
+    "no_load",
+    "no_additional_ints",
+    "enable_state_current_unscaled",
+    "unused",
+];
+
 #[derive(Default)]
 pub(crate) struct SerializableBuilding<'a> {
     pub(crate) roots: Vec<SerializableRoot<'a>>,
@@ -184,12 +197,12 @@ fn write_metadata<W: Write>(mut w: W, block: &SerializableBlock, building: &Seri
     // Values count + values
     w.write_vec::<u16, f32, LE>(&metadata.values)?;
 
-    // Vector flag + fields count
+    // Fields count, offset by 0x7FFF when vectors follow
     let fields_len: u16 = metadata.fields.len().try_into()?;
     if fields_len >= u16::MAX / 2 {
         return Err(Box::new(TooManyValues));
     }
-    w.write_num::<u16, LE>(fields_len | if metadata.vectors.is_empty() {0} else {u16::MAX / 2})?;
+    w.write_num::<u16, LE>(fields_len + if metadata.vectors.is_empty() {0} else {u16::MAX / 2})?;
 
     // Vectors count + vectors
     if !metadata.vectors.is_empty() {
@@ -256,178 +269,206 @@ pub(crate) fn write_type_settings<W: Write>(mut w: W, block: &SerializableBlock,
     Ok(())
 }
 
-pub(crate) fn read_building<R: Read>(mut r: R) -> Result<Building> {
+pub(crate) fn read_building<R: FieldReader>(mut r: R) -> Result<Building> {
     let mut building = SerializableBuilding::default();
 
     let roots_count = r.read_num::<u16, LE>()?;
     info!("Root count: {roots_count}");
+    r.record("roots.len", &roots_count);
     building.roots.reserve(roots_count as usize);
     for i in 0..roots_count {
         trace!("Reading root at index {i}");
+        r.push_scope(format_args!("roots[{i}]"));
         building.roots.push(read_root(&mut r, &building)?);
+        r.pop_scope();
     }
 
     let blocks_count = r.read_num::<u16, LE>()?;
     info!("Block count: {blocks_count}");
+    r.record("blocks.len", &blocks_count);
     building.blocks.reserve(blocks_count as usize);
     for i in 0..blocks_count {
         trace!("Reading block at index {i}");
+        r.push_scope(format_args!("blocks[{i}]"));
         building.blocks.push(read_block(&mut r, &building)?);
+        r.pop_scope();
     }
     
     Ok(building.into_building()?)
 }
 
-fn read_root<'a, R: Read>(mut r: R, building: &SerializableBuilding) -> Result<SerializableRoot<'a>> {
+fn read_root<'a, R: FieldReader>(mut r: R, building: &SerializableBuilding) -> Result<SerializableRoot<'a>> {
     let mut root = SerializableRoot::default();
 
     root.position = r.read_array::<f32, LE, 3>()?;
-    trace!("Position: {:?}", root.position);
+    r.record("position", &root.position);
     root.rotation = r.read_array::<f32, LE, 3>()?;
-    trace!("Rotation: {:?}", root.rotation);
+    r.record("rotation", &root.rotation);
 
     Ok(root)
 }
 
-fn read_block<'a, R: Read>(mut r: R, building: &SerializableBuilding) -> Result<SerializableBlock<'a>> {
+fn read_block<'a, R: FieldReader>(mut r: R, building: &SerializableBuilding) -> Result<SerializableBlock<'a>> {
     let mut block = SerializableBlock::default();
 
     block.position = r.read_array::<f32, LE, 3>()?;
-    trace!("Position: {:?}", block.position);
+    r.record("position", &block.position);
     block.rotation = unpack_rotation(r.read_array::<u16, LE, 3>()?);
-    trace!("Rotation: {:?}", block.rotation);
+    r.record("rotation", &block.rotation);
 
     block.id = r.read_num::<u8, LE>()?;
-    trace!("Type ID: {}", block.id);
+    r.record("id", &block.id);
 
     block.root = r.read_num::<u8, LE>()?.into();
-    trace!("Root index: {}", block.root);
+    r.record("root", &block.root);
 
-    let flags = unpack_bools(&[r.read_num::<u8, LE>()?], 8);
-    trace!("Flags: {:?}", flags);
+    let flags_byte = r.read_num::<u8, LE>()?;
+    let flags = unpack_bools(&[flags_byte], 8);
+    let bits: Vec<(&str, bool)> = BLOCK_FLAGS.iter().copied().zip(flags.iter().copied()).collect();
+    r.record_bits("flags", &format_args!("{flags_byte:#010b}"), &bits);
 
     block.enable_state_current = r.read_num::<u8, LE>()? as f32 / if flags[6] {1.0f32} else {255.0f32};
-    trace!("Enable state current: {}", block.enable_state_current);
+    r.record("enable_state_current", &block.enable_state_current);
 
     if flags[0] {
         block.name = r.read_string_7bit()?;
-        trace!("Name: {}", block.name);
+        r.record("name", &block.name);
     }
 
     block.enable_state = r.read_num::<u8, LE>()? as f32 / 255.0f32;
-    trace!("Enable state: {}", block.enable_state);
+    r.record("enable_state", &block.enable_state);
 
     if !flags[4] {
-        block.load = Some(r.read_num::<u16, LE>()?);
-        trace!("Load block index: {}", block.load.unwrap());
+        let load = r.read_num::<u16, LE>()?;
+        r.record("load", &load);
+        block.load = Some(load);
     }
 
     if flags[1] {
-        block.connections = r.read_vec::<u16, u16, LE>()?;
-        trace!("Connections: {:?}", block.connections);
+        block.connections = r.read_vec_field::<u16, u16>("connections")?;
     }
 
     // Additional ints (we dont need this, at least idk what it's used for).
     if !flags[5] {
-        _ = r.read_vec::<u16, i32, LE>()?;
+        _ = r.read_vec_field::<u16, i32>("additional_ints")?;
     }
 
     if !flags[2] {
+        r.push_scope("metadata");
         block.metadata = Some(read_metadata(&mut r, &block, building)?);
+        r.pop_scope();
     }
 
     if !flags[3] {
-        block.color = Some(r.read_array::<u8, LE, 4>()?);
-        trace!("Color: {:?}", block.color.unwrap());
+        let color = r.read_array::<u8, LE, 4>()?;
+        r.record("color", &color);
+        block.color = Some(color);
     }
     
     Ok(block)
 }
 
-fn read_metadata<R: Read>(mut r: R, block: &SerializableBlock, building: &SerializableBuilding) -> Result<Metadata> {
+fn read_metadata<R: FieldReader>(mut r: R, block: &SerializableBlock, building: &SerializableBuilding) -> Result<Metadata> {
     let mut metadata = Metadata::default();
 
     // Toggles count + toggles
-    metadata.toggles = r.read_vec::<u16, u8, LE>()?.iter().map(|&v| v != 0).collect();
-    trace!("Toggles: {:?}", metadata.toggles);
+    metadata.toggles = r.read_vec_field::<u16, u8>("toggles")?.iter().map(|&v| v != 0).collect();
 
     // Values count + values
-    metadata.values = r.read_vec::<u16, f32, LE>()?;
-    trace!("Values: {:?}", metadata.values);
+    metadata.values = r.read_vec_field::<u16, f32>("values")?;
 
-    // Vector flag + fields count
+    // Fields count, offset by 0x7FFF when vectors follow
     let vec_field_ctrl = r.read_num::<u16, LE>()?;
-    trace!("Vector-field control value: {}", vec_field_ctrl);
+    r.record_bits(
+        "vector_field_control",
+        &(vec_field_ctrl % 0x7FFF),
+        &[("has_vectors", vec_field_ctrl >= 0x7FFF)]
+    );
 
     // Vectors count + vectors
     if vec_field_ctrl >= 0x7FFF {
-        let vectors_len = r.read_num::<u16, LE>()? as usize;
-        for _ in 0..vectors_len {
-            metadata.vectors.push(r.read_array::<f32, LE, 3>()?);
+        let vectors_len = r.read_num::<u16, LE>()?;
+        r.record("vectors.len", &vectors_len);
+        for i in 0..vectors_len {
+            let vector = r.read_array::<f32, LE, 3>()?;
+            r.record(format_args!("vectors[{i}]"), &vector);
+            metadata.vectors.push(vector);
         }
-        trace!("Vectors: {:?}", metadata.vectors);
     }
 
     // Fields
     let fields_len = (vec_field_ctrl % 0x7FFF) as usize;
     metadata.fields.reserve(fields_len);
-    for _ in 0..fields_len {
-        metadata.fields.push(r.read_vec::<u16, i32, LE>()?.into_vec_lossy());
+    for i in 0..fields_len {
+        metadata.fields.push(r.read_vec_field::<u16, i32>(format_args!("fields[{i}]"))?.into_vec_lossy());
     }
-    trace!("Fields: {:?}", metadata.fields);
 
     // Dropdowns
-    metadata.dropdowns = r.read_vec::<u16, i32, LE>()?.into_vec_lossy();
-    trace!("Dropdowns: {:?}", metadata.dropdowns);
+    metadata.dropdowns = r.read_vec_field::<u16, i32>("dropdowns")?.into_vec_lossy();
 
     // Colors
-    let colors_len = r.read_num::<u16, LE>()? as usize;
-    for _ in 0..colors_len {
-        metadata.colors.push(r.read_array::<u8, LE, 4>()?);
+    let colors_len = r.read_num::<u16, LE>()?;
+    r.record("colors.len", &colors_len);
+    for i in 0..colors_len {
+        let color = r.read_array::<u8, LE, 4>()?;
+        r.record(format_args!("colors[{i}]"), &color);
+        metadata.colors.push(color);
     }
-    trace!("Colors: {:?}", metadata.colors);
 
     // Gradients
-    let gradients_len = r.read_num::<u16, LE>()? as usize;
-    for _ in 0..gradients_len {
+    let gradients_len = r.read_num::<u16, LE>()?;
+    r.record("gradients.len", &gradients_len);
+    for i in 0..gradients_len {
+        r.push_scope(format_args!("gradients[{i}]"));
         metadata.gradients.push(read_gradient(&mut r)?);
+        r.pop_scope();
     }
-    trace!("Gradients: {:?}", metadata.gradients);
 
-    read_type_settings(&mut r, &block, &building)?;
+    metadata.type_settings = read_type_settings(&mut r, &block, &building)?;
 
     Ok(metadata)
 }
 
-fn read_gradient<R: Read>(mut r: R) -> Result<Gradient> {
+fn read_gradient<R: FieldReader>(mut r: R) -> Result<Gradient> {
     let mut gradient = Gradient::default();
 
-    let color_keys_len: usize = r.read_num::<u16, LE>()?.into();
-    gradient.color_keys.reserve(color_keys_len);
-    for _ in 0..color_keys_len {
-        gradient.color_keys.push(r.read_array::<u8, LE, 4>()?);
+    let color_keys_len = r.read_num::<u16, LE>()?;
+    r.record("color_keys.len", &color_keys_len);
+    gradient.color_keys.reserve(color_keys_len.into());
+    for i in 0..color_keys_len {
+        let color = r.read_array::<u8, LE, 4>()?;
+        r.record(format_args!("color_keys[{i}]"), &color);
+        gradient.color_keys.push(color);
     }
 
-    gradient.color_time_keys = r.read_vec::<u16, f32, LE>()?;
-    gradient.alpha_keys = r.read_vec::<u16, f32, LE>()?;
-    gradient.alpha_time_keys = r.read_vec::<u16, f32, LE>()?;
+    gradient.color_time_keys = r.read_vec_field::<u16, f32>("color_time_keys")?;
+    gradient.alpha_keys = r.read_vec_field::<u16, f32>("alpha_keys")?;
+    gradient.alpha_time_keys = r.read_vec_field::<u16, f32>("alpha_time_keys")?;
     
     Ok(gradient)
 }
 
-fn read_type_settings<R: Read>(mut r: R, block: &SerializableBlock, building: &SerializableBuilding) -> Result<TypeSettings> {
+fn read_type_settings<R: FieldReader>(mut r: R, block: &SerializableBlock, building: &SerializableBuilding) -> Result<TypeSettings> {
     let mut type_settings = TypeSettings::None;
     
     match block.id {
         129 => {
+            r.push_scope("type_settings");
+            let function_len = r.read_num::<u16, LE>()?;
+            r.record("function.len", &function_len);
+            let mut function = vec![0u8; function_len as usize];
+            r.read_exact(&mut function)?;
+            let function = String::from_utf8(function)?;
+            r.record("function", &function);
             type_settings = TypeSettings::MathBlock {
-                function                   : String::from_utf8(r.read_vec::<u16, u8, LE>()?)?,
-                incoming_connections_order : r.read_vec::<u8, u8, LE>()?,
-                slots                      : r.read_vec::<u8, u8, LE>()?
-            }
+                function,
+                incoming_connections_order : r.read_vec_field::<u8, u8>("incoming_connections_order")?,
+                slots                      : r.read_vec_field::<u8, u8>("slots")?
+            };
+            r.pop_scope();
         },
         _ => {}
     }
 
     Ok(type_settings)
-}
\ No newline at end of file
+}
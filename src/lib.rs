@@ -34,4 +34,21 @@
 //! ```
 
 pub mod structs;
-pub mod io;
\ No newline at end of file
+pub mod id;
+pub mod bounds;
+pub mod builder;
+pub mod io;
+pub mod validate;
+pub mod diff;
+pub mod edit;
+pub mod patch;
+pub mod transform;
+pub mod math;
+pub mod spatial;
+pub mod duplicates;
+pub mod adjacency;
+pub mod hierarchy;
+pub mod mass;
+pub mod graph;
+
+mod union_find;
\ No newline at end of file
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::adjacency::SizeTable;
+use crate::id::RootId;
+use crate::io::NOT_INTERACTABLE;
+use crate::math::{self, Mat3};
+use crate::structs::*;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Mass of every block type.
+///
+/// [`MassTable::default`] is the built-in table of rough estimates, see
+/// [`MassTable::builtin`]; [`MassTable::new`] starts from an empty one.
+/// Either is completed or corrected with [`MassTable::with_mass`]. Results
+/// are in the units of the table.
+pub struct MassTable {
+    default: f32,
+    masses: HashMap<u8, f32>,
+}
+
+impl Default for MassTable {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl MassTable {
+    /// A table where every type weighs `default`.
+    pub fn new(default: f32) -> Self {
+        Self { default, masses: HashMap::new() }
+    }
+
+    /// The built-in table, in units of a structural block.
+    ///
+    /// These are rough estimates, not masses measured in the game: the
+    /// structural types (the ones that can not be interacted with) weigh
+    /// `1.0`, and every other type (motors, sensors, lamps, logic…) weighs
+    /// `0.5`. They are good enough to see which way a contraption
+    /// leans; override the types whose mass you know.
+    pub fn builtin() -> Self {
+        let mut table = Self::new(0.5);
+        table.masses.extend(NOT_INTERACTABLE.iter().map(|&id| (id, 1.0)));
+        table
+    }
+
+    /// Sets the mass of a block type.
+    pub fn with_mass(mut self, id: u8, mass: f32) -> Self {
+        self.masses.insert(id, mass);
+        self
+    }
+
+    /// Mass of a block type.
+    pub fn mass(&self, id: u8) -> f32 {
+        self.masses.get(&id).copied().unwrap_or(self.default)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+/// Mass, center of mass and inertia of a set of blocks.
+pub struct MassProperties {
+    pub mass: f32,
+
+    /// Center of mass in building space; the origin when there is no mass.
+    pub center: [f32; 3],
+
+    /// Inertia tensor about the center of mass, along the building axes.
+    pub inertia: Mat3,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Result of [`Building::mass_properties`].
+pub struct MassReport {
+    /// Properties of every root, in root order.
+    pub roots: Vec<MassProperties>,
+
+    /// Properties of the whole building.
+    pub building: MassProperties,
+}
+
+impl MassProperties {
+    /// Properties of `blocks`, each a uniform box sized by `sizes`.
+    pub fn of<'a>(blocks: impl IntoIterator<Item = &'a Block>, masses: &MassTable, sizes: &SizeTable) -> Self {
+        let blocks: Vec<&Block> = blocks.into_iter().collect();
+
+        let mass: f32 = blocks.iter().map(|block| masses.mass(block.id)).sum();
+        if mass == 0.0 {
+            return Self::default();
+        }
+        let mut center = [0.0; 3];
+        for block in blocks.iter() {
+            let weight = masses.mass(block.id) / mass;
+            for (c, p) in center.iter_mut().zip(block.position) {
+                *c += p * weight;
+            }
+        }
+
+        // Every block is summed about the center of mass, rather than about
+        // the origin and shifted once: buildings far from the origin would
+        // lose precision.
+        let mut inertia = [[0.0; 3]; 3];
+        for block in blocks.iter() {
+            let block_mass = masses.mass(block.id);
+            let offset = [0, 1, 2].map(|i| block.position[i] - center[i]);
+            let local = box_inertia(block_mass, sizes.size(block.id));
+            let rotation = math::euler_to_matrix(block.rotation);
+            let rotated = math::mat_mul(math::mat_mul(rotation, local), math::transpose(rotation));
+            let shifted = point_inertia(block_mass, offset);
+            for row in 0..3 {
+                for col in 0..3 {
+                    inertia[row][col] += rotated[row][col] + shifted[row][col];
+                }
+            }
+        }
+
+        Self { mass, center, inertia }
+    }
+}
+
+/// Inertia of a uniform box about its center, along its own axes.
+fn box_inertia(mass: f32, size: [f32; 3]) -> Mat3 {
+    let [x, y, z] = size.map(|s| s * s * mass / 12.0);
+    [[y + z, 0.0, 0.0], [0.0, x + z, 0.0], [0.0, 0.0, x + y]]
+}
+
+/// Inertia of a point mass at `offset` (the parallel axis term).
+fn point_inertia(mass: f32, offset: [f32; 3]) -> Mat3 {
+    let squared: f32 = offset.iter().map(|v| v * v).sum();
+    let mut inertia = [[0.0; 3]; 3];
+    for row in 0..3 {
+        for col in 0..3 {
+            let diagonal = if row == col { squared } else { 0.0 };
+            inertia[row][col] = mass * (diagonal - offset[row] * offset[col]);
+        }
+    }
+    inertia
+}
+
+impl Building {
+    /// Mass, center of mass and inertia of every root and of the whole
+    /// building, treating every block as a uniform box of its type's mass
+    /// and size.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    /// use sw_structure_io::adjacency::SizeTable;
+    /// use sw_structure_io::mass::MassTable;
+    ///
+    /// let mut building = Building::default();
+    /// building.roots.push(Root::default());
+    /// building.blocks.push(Block { id: 0, position: [0.0, 0.0, 0.0], ..Default::default() });
+    /// building.blocks.push(Block { id: 1, position: [4.0, 0.0, 0.0], ..Default::default() });
+    ///
+    /// let masses = MassTable::default().with_mass(1, 3.0);
+    /// let report = building.mass_properties(&masses, &SizeTable::default());
+    /// assert_eq!(report.building.mass, 4.0);
+    /// assert_eq!(report.building.center, [3.0, 0.0, 0.0]);
+    /// ```
+    pub fn mass_properties(&self, masses: &MassTable, sizes: &SizeTable) -> MassReport {
+        // Blocks of roots that do not exist only count towards the building.
+        let mut blocks: Vec<Vec<&Block>> = vec![Vec::new(); self.iter_roots().count()];
+        for (_, block) in self.iter_blocks() {
+            if let Some(blocks) = blocks.get_mut(block.root as usize) {
+                blocks.push(block);
+            }
+        }
+        let roots = blocks.into_iter().map(|blocks| MassProperties::of(blocks, masses, sizes)).collect();
+        let building = MassProperties::of(self.blocks.iter(), masses, sizes);
+        MassReport { roots, building }
+    }
+
+    /// Mass, center of mass and inertia of the blocks of a single root.
+    pub fn root_mass_properties(&self, root: RootId, masses: &MassTable, sizes: &SizeTable) -> MassProperties {
+        MassProperties::of(self.blocks_of(root).map(|(_, block)| block), masses, sizes)
+    }
+}
@@ -0,0 +1,291 @@
+//! Rotation math for the Euler angles stored in buildings.
+//!
+//! # Convention
+//!
+//! Buildings use Unity's coordinate system and Euler convention:
+//!
+//! - Coordinates are left-handed: +X is right, +Y is up, +Z is forward.
+//! - `rotation: [x, y, z]` holds angles in degrees.
+//! - Rotations are applied around Z first, then X, then Y, all around the
+//!   world axes. As quaternions, `q = qy * qx * qz`; as matrices,
+//!   `R = Ry * Rx * Rz` acting on column vectors.
+//! - A positive angle turns clockwise when looking down the axis towards the
+//!   origin, e.g. +90° around Y turns forward (+Z) into right (+X).
+//!
+//! Converting back to Euler angles yields angles in `[0, 360)`. The stored
+//! angles are quantized on write (see the `io` module); these functions work
+//! on unquantized values.
+//!
+//! # Example
+//! ```rust
+//! use sw_structure_io::math;
+//!
+//! let q = math::from_euler([0.0, 90.0, 0.0]);
+//! let forward = math::rotate(q, [0.0, 0.0, 1.0]);
+//! assert!((forward[0] - 1.0).abs() < 1e-6);
+//! ```
+
+use std::sync::LazyLock;
+
+use crate::transform::Axis;
+
+/// A rotation quaternion as `[x, y, z, w]`.
+pub type Quat = [f32; 4];
+
+/// A row-major 3x3 matrix, acting on column vectors.
+pub type Mat3 = [[f32; 3]; 3];
+
+/// The quaternion that does not rotate.
+pub const IDENTITY: Quat = [0.0, 0.0, 0.0, 1.0];
+
+/// Canonical angles this close to a whole degree are snapped to it.
+const DEGREE_SNAP: f32 = 1e-3;
+
+/// Angle in degrees below which [`canonical_euler`] keeps canonical input.
+/// Larger than [`DEGREE_SNAP`], since snapping alone moves every angle by up
+/// to that much.
+const SAME_ORIENTATION: f32 = 1e-2;
+
+/// The 24 orientations that map every axis onto an axis, as canonical Euler
+/// angles (all multiples of 90°).
+static AXIS_ALIGNED: LazyLock<Vec<[f32; 3]>> = LazyLock::new(|| {
+    let quarters = [0.0, 90.0, 180.0, 270.0];
+    let mut orientations: Vec<[f32; 3]> = Vec::with_capacity(24);
+    for x in quarters {
+        for y in quarters {
+            for z in quarters {
+                let euler = canonical_euler([x, y, z]).map(|a| normalize_degrees((a / 90.0).round() * 90.0));
+                if !orientations.contains(&euler) {
+                    orientations.push(euler);
+                }
+            }
+        }
+    }
+    orientations
+});
+
+/// Rotation by `degrees` around a single axis.
+pub fn from_axis_angle(axis: Axis, degrees: f32) -> Quat {
+    let (s, c) = (degrees.to_radians() / 2.0).sin_cos();
+    let mut q = [0.0, 0.0, 0.0, c];
+    q[axis.index()] = s;
+    q
+}
+
+/// Hamilton product `a * b`: rotates by `b`, then by `a`.
+pub fn mul(a: Quat, b: Quat) -> Quat {
+    let [ax, ay, az, aw] = a;
+    let [bx, by, bz, bw] = b;
+    [
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ]
+}
+
+/// Inverse of a unit quaternion.
+pub fn inverse(q: Quat) -> Quat {
+    [-q[0], -q[1], -q[2], q[3]]
+}
+
+/// Scales a quaternion to unit length, to undo drift after many products.
+pub fn normalize(q: Quat) -> Quat {
+    let length = q.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if length == 0.0 { IDENTITY } else { q.map(|v| v / length) }
+}
+
+/// Converts Euler angles in degrees to a quaternion.
+pub fn from_euler(euler: [f32; 3]) -> Quat {
+    mul(mul(from_axis_angle(Axis::Y, euler[1]), from_axis_angle(Axis::X, euler[0])), from_axis_angle(Axis::Z, euler[2]))
+}
+
+/// Converts a unit quaternion to Euler angles in degrees, each in `[0, 360)`.
+///
+/// At the gimbal lock (X at ±90°) the Z angle is set to zero.
+pub fn to_euler(q: Quat) -> [f32; 3] {
+    matrix_to_euler(to_matrix(q))
+}
+
+/// Converts a unit quaternion to a rotation matrix.
+pub fn to_matrix(q: Quat) -> Mat3 {
+    let [x, y, z, w] = q;
+    [
+        [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w)],
+        [2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w)],
+        [2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y)],
+    ]
+}
+
+/// Converts a rotation matrix to a unit quaternion.
+pub fn from_matrix(m: Mat3) -> Quat {
+    let trace = m[0][0] + m[1][1] + m[2][2];
+    // Divide by the largest of the four candidates to stay precise.
+    let q = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [(m[2][1] - m[1][2]) / s, (m[0][2] - m[2][0]) / s, (m[1][0] - m[0][1]) / s, s / 4.0]
+    } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+        let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+        [s / 4.0, (m[0][1] + m[1][0]) / s, (m[0][2] + m[2][0]) / s, (m[2][1] - m[1][2]) / s]
+    } else if m[1][1] > m[2][2] {
+        let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+        [(m[0][1] + m[1][0]) / s, s / 4.0, (m[1][2] + m[2][1]) / s, (m[0][2] - m[2][0]) / s]
+    } else {
+        let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+        [(m[0][2] + m[2][0]) / s, (m[1][2] + m[2][1]) / s, s / 4.0, (m[1][0] - m[0][1]) / s]
+    };
+    normalize(q)
+}
+
+/// Converts Euler angles in degrees to a rotation matrix.
+pub fn euler_to_matrix(euler: [f32; 3]) -> Mat3 {
+    to_matrix(from_euler(euler))
+}
+
+/// Converts a rotation matrix to Euler angles in degrees, each in `[0, 360)`.
+///
+/// At the gimbal lock (X at ±90°) the Z angle is set to zero.
+pub fn matrix_to_euler(m: Mat3) -> [f32; 3] {
+    // `atan2` keeps X precise near ±90°, where `asin` is not.
+    let cos_x = m[1][0].hypot(m[1][1]);
+    let x = (-m[1][2]).atan2(cos_x);
+    // At the gimbal lock, Z is set to zero.
+    let z = if cos_x > 1e-5 { m[1][0].atan2(m[1][1]) } else { 0.0 };
+    let y = if cos_x > 0.5 {
+        m[0][2].atan2(m[2][2])
+    } else {
+        // Near the lock the elements above are tiny, and Y taken from them
+        // would not match Z. The first column holds the Y angle shifted by
+        // an angle of X and Z, which stays precise.
+        let sin_x = -m[1][2];
+        (-m[2][0]).atan2(m[0][0]) + (sin_x * z.sin()).atan2(z.cos())
+    };
+    [x, y, z].map(|a| normalize_degrees(a.to_degrees()))
+}
+
+/// Composes two Euler rotations: rotates by `first`, then by `then`.
+pub fn compose_euler(first: [f32; 3], then: [f32; 3]) -> [f32; 3] {
+    to_euler(mul(from_euler(then), from_euler(first)))
+}
+
+/// Returns the canonical Euler angles of an orientation.
+///
+/// Every orientation has many Euler triples (e.g. `[180, 0, 0]` and
+/// `[0, 180, 180]`); this picks the one [`to_euler`] produces, with X in
+/// `[0, 90]` or `[270, 360)`, and snaps
+/// angles within 0.001° of a whole degree to it so float noise does not
+/// split equal orientations. At the gimbal lock, Z is zero.
+///
+/// Angles already in canonical form are returned unchanged, so the result
+/// is a fixed point: converting it back and forth would otherwise move it
+/// by float noise.
+///
+/// # Example
+/// ```rust
+/// use sw_structure_io::math::canonical_euler;
+///
+/// assert_eq!(canonical_euler([180.0, 0.0, 0.0]), [0.0, 180.0, 180.0]);
+/// assert_eq!(canonical_euler([-90.0, 0.0, 0.0]), [270.0, 0.0, 0.0]);
+/// ```
+pub fn canonical_euler(euler: [f32; 3]) -> [f32; 3] {
+    let snap = |euler: [f32; 3]| euler.map(|angle| {
+        let whole = angle.round();
+        if (angle - whole).abs() < DEGREE_SNAP { normalize_degrees(whole) } else { angle }
+    });
+    let mut canonical = snap(to_euler(from_euler(euler)));
+    if is_gimbal_lock(canonical[0]) && canonical[2] != 0.0 {
+        // X was snapped onto the lock: fold Z into Y, as `to_euler` does there.
+        canonical = snap(to_euler(from_euler(canonical)));
+    }
+
+    let same = angle_between(from_euler(euler), from_euler(canonical)) < SAME_ORIENTATION;
+    if same && is_canonical(euler) { euler } else { canonical }
+}
+
+fn is_gimbal_lock(x: f32) -> bool {
+    x == 90.0 || x == 270.0
+}
+
+/// Whether `euler` has the form [`canonical_euler`] produces.
+fn is_canonical(euler: [f32; 3]) -> bool {
+    let in_range = euler.iter().all(|angle| (0.0..360.0).contains(angle));
+    let snapped = euler.iter().all(|angle| angle.fract() == 0.0 || (angle - angle.round()).abs() >= DEGREE_SNAP);
+    let x = euler[0];
+    in_range && snapped && (x <= 90.0 || x >= 270.0) && (!is_gimbal_lock(x) || euler[2] == 0.0)
+}
+
+/// Angle in degrees of the rotation from `a` to `b`.
+pub fn angle_between(a: Quat, b: Quat) -> f32 {
+    // `atan2` of the difference stays precise for tiny angles, where `acos` is not.
+    let [x, y, z, w] = mul(inverse(a), b);
+    2.0 * (x * x + y * y + z * z).sqrt().atan2(w.abs()).to_degrees()
+}
+
+/// The 24 axis-aligned orientations, as canonical Euler angles.
+pub fn axis_aligned_orientations() -> &'static [[f32; 3]] {
+    &AXIS_ALIGNED
+}
+
+/// Returns the axis-aligned orientation closest to `euler`, if it is within
+/// `tolerance` degrees.
+pub fn snap_to_axes(euler: [f32; 3], tolerance: f32) -> Option<[f32; 3]> {
+    let q = from_euler(euler);
+    AXIS_ALIGNED
+        .iter()
+        .map(|&aligned| (aligned, angle_between(q, from_euler(aligned))))
+        .filter(|&(_, angle)| angle <= tolerance)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(aligned, _)| aligned)
+}
+
+/// Turns `-0.0` into `0.0`, leaving every other value as it is, so results
+/// compare and print the same whatever the sign of a zero.
+pub(crate) fn positive_zero(v: f32) -> f32 {
+    v + 0.0
+}
+
+/// Wraps an angle in degrees into `[0, 360)`.
+pub fn normalize_degrees(angle: f32) -> f32 {
+    let wrapped = positive_zero(angle.rem_euclid(360.0));
+    // `rem_euclid` can round up to exactly 360 for tiny negative angles.
+    if wrapped >= 360.0 { 0.0 } else { wrapped }
+}
+
+/// Rotates a vector by a unit quaternion.
+pub fn rotate(q: Quat, v: [f32; 3]) -> [f32; 3] {
+    mat_vec(to_matrix(q), v)
+}
+
+/// Product of a matrix and a column vector.
+pub fn mat_vec(m: Mat3, v: [f32; 3]) -> [f32; 3] {
+    [0, 1, 2].map(|row| m[row][0] * v[0] + m[row][1] * v[1] + m[row][2] * v[2])
+}
+
+/// Matrix product `a * b`: applies `b`, then `a`.
+pub fn mat_mul(a: Mat3, b: Mat3) -> Mat3 {
+    [0, 1, 2].map(|row| [0, 1, 2].map(|col| (0..3).map(|k| a[row][k] * b[k][col]).sum()))
+}
+
+/// Transposed matrix, which is the inverse of a rotation matrix.
+pub fn transpose(m: Mat3) -> Mat3 {
+    [0, 1, 2].map(|row| [0, 1, 2].map(|col| m[col][row]))
+}
+
+/// Orientation of a block mirrored across the plane normal to `axis`.
+///
+/// For a reflection `M`, this is `M * R * Mx`: the block's forward (Z) and
+/// up (Y) axes are mirrored, and its local X axis is reversed to keep the
+/// rotation proper.
+pub fn mirror(q: Quat, axis: Axis) -> Quat {
+    // `M * R * M` keeps the component along the axis and negates the others.
+    let mut conjugated = q.map(|v| -v);
+    conjugated[axis.index()] = q[axis.index()];
+    conjugated[3] = q[3];
+
+    // `M * Mx` is a half turn around the remaining axis (or nothing for X).
+    match axis {
+        Axis::X => conjugated,
+        Axis::Y => mul(conjugated, [0.0, 0.0, 1.0, 0.0]),
+        Axis::Z => mul(conjugated, [0.0, 1.0, 0.0, 0.0]),
+    }
+}
@@ -0,0 +1,742 @@
+use std::collections::{HashMap, HashSet};
+use std::mem::discriminant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::diff::match_buildings;
+use crate::structs::*;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum PatchError {
+    #[error("Patch references root {root} which is not in the building")]
+    RootOutOfRange {
+        root: u16
+    },
+    #[error("Patch references block {block} which is not in the building")]
+    BlockOutOfRange {
+        block: u16
+    },
+    #[error("Patch references added item {index} but only adds {count}")]
+    AddedOutOfRange {
+        index: u16,
+        count: usize
+    },
+    #[error("Patch references root {root} which it removes")]
+    RemovedRoot {
+        root: u16
+    },
+    #[error("Patch references block {block} which it removes")]
+    RemovedBlock {
+        block: u16
+    },
+    #[error("Building has too many roots or blocks after applying the patch")]
+    TooManyValues,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// A root or block reference inside a [`Patch`].
+///
+/// References either point into the building the patch is applied to, or to
+/// something added by the patch itself, so they stay valid no matter how
+/// indices shift when the patch is applied.
+pub enum Ref {
+    /// Index in the building the patch is applied to.
+    Base(u16),
+
+    /// Index in the patch's list of added roots or blocks.
+    Added(u16),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A single field of a block, with block and root references as [`Ref`]s.
+pub enum BlockField {
+    Position([f32; 3]),
+    Rotation([f32; 3]),
+    Id(u8),
+    Root(Ref),
+    Name(String),
+    EnableState(f32),
+    EnableStateCurrent(f32),
+    Connections(Vec<Ref>),
+    Load(Option<Ref>),
+    Color(Option<[u8; 4]>),
+
+    /// Block metadata. Its own `fields` and math block
+    /// `incoming_connections_order` are ignored, the reference lists stored
+    /// here are used instead.
+    Metadata {
+        metadata: Option<Box<Metadata>>,
+        fields: Vec<Vec<Ref>>,
+        incoming_connections_order: Vec<Ref>,
+    },
+}
+
+impl BlockField {
+    /// Name of the block field this value sets.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockField::Position(_) => "position",
+            BlockField::Rotation(_) => "rotation",
+            BlockField::Id(_) => "id",
+            BlockField::Root(_) => "root",
+            BlockField::Name(_) => "name",
+            BlockField::EnableState(_) => "enable_state",
+            BlockField::EnableStateCurrent(_) => "enable_state_current",
+            BlockField::Connections(_) => "connections",
+            BlockField::Load(_) => "load",
+            BlockField::Color(_) => "color",
+            BlockField::Metadata { .. } => "metadata",
+        }
+    }
+
+    fn refs_mut(&mut self) -> Vec<&mut Ref> {
+        match self {
+            BlockField::Connections(refs) => refs.iter_mut().collect(),
+            BlockField::Load(Some(r)) => vec![r],
+            BlockField::Metadata { fields, incoming_connections_order, .. } => fields
+                .iter_mut()
+                .flatten()
+                .chain(incoming_connections_order.iter_mut())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn retain_refs(&mut self, mut keep: impl FnMut(&Ref) -> bool) {
+        match self {
+            BlockField::Connections(refs) => refs.retain(&mut keep),
+            BlockField::Load(load) if load.as_ref().is_some_and(|r| !keep(r)) => *load = None,
+            BlockField::Metadata { metadata, fields, incoming_connections_order } => {
+                for items in fields.iter_mut() {
+                    items.retain(&mut keep);
+                }
+                let slots = match metadata.as_mut().map(|m| &mut m.type_settings) {
+                    Some(TypeSettings::MathBlock { slots, .. }) => Some(slots),
+                    _ => None,
+                };
+                let mut kept_slots = Vec::new();
+                let mut i = 0;
+                incoming_connections_order.retain(|r| {
+                    let kept = keep(r);
+                    if let Some(&slot) = slots.as_ref().and_then(|s| s.get(i)) && kept {
+                        kept_slots.push(slot);
+                    }
+                    i += 1;
+                    kept
+                });
+                if let Some(slots) = slots {
+                    kept_slots.extend(slots.iter().skip(i));
+                    *slots = kept_slots;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Describes a block as its full list of fields.
+///
+/// References `block_ref` can not resolve are left out, and their targets
+/// pushed to `dangling`.
+fn describe(
+    block: &Block,
+    block_ref: impl Fn(u16) -> Option<Ref>,
+    root_ref: impl Fn(u16) -> Ref,
+    dangling: &mut Vec<u16>,
+) -> Vec<BlockField> {
+    let resolve = |i: u16, dangling: &mut Vec<u16>| {
+        let r = block_ref(i);
+        if r.is_none() {
+            dangling.push(i);
+        }
+        r
+    };
+    let refs = |items: &[u16], dangling: &mut Vec<u16>| {
+        items.iter().filter_map(|&i| resolve(i, dangling)).collect::<Vec<Ref>>()
+    };
+
+    let metadata = block.metadata.clone().map(|mut metadata| {
+        let fields: Vec<Vec<Ref>> = metadata.fields.iter().map(|items| refs(items, dangling)).collect();
+        metadata.fields.iter_mut().for_each(Vec::clear);
+        let order = match &mut metadata.type_settings {
+            TypeSettings::MathBlock { incoming_connections_order, slots, .. } => {
+                // Slots of left out references are left out with them.
+                let order = std::mem::take(incoming_connections_order);
+                let mut kept = Vec::new();
+                let mut kept_slots = Vec::new();
+                for (i, &target) in order.iter().enumerate() {
+                    if let Some(r) = resolve(target.into(), dangling) {
+                        kept.push(r);
+                        kept_slots.extend(slots.get(i));
+                    }
+                }
+                kept_slots.extend(slots.iter().skip(order.len()));
+                *slots = kept_slots;
+                kept
+            }
+            TypeSettings::None => Vec::new(),
+        };
+        (metadata, fields, order)
+    });
+    let (metadata, fields, incoming_connections_order) = match metadata {
+        Some((metadata, fields, order)) => (Some(Box::new(metadata)), fields, order),
+        None => (None, Vec::new(), Vec::new()),
+    };
+    let connections = refs(&block.connections, dangling);
+    let load = block.load.and_then(|i| resolve(i, dangling));
+
+    vec![
+        BlockField::Position(block.position),
+        BlockField::Rotation(block.rotation),
+        BlockField::Id(block.id),
+        BlockField::Root(root_ref(block.root)),
+        BlockField::Name(block.name.clone()),
+        BlockField::EnableState(block.enable_state),
+        BlockField::EnableStateCurrent(block.enable_state_current),
+        BlockField::Connections(connections),
+        BlockField::Load(load),
+        BlockField::Color(block.color),
+        BlockField::Metadata { metadata, fields, incoming_connections_order },
+    ]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A reference to a block that is not in the building, left out of a patch by
+/// [`Patch::compute_with_dangling`].
+pub struct DanglingReference {
+    /// Block holding the reference.
+    pub block: u16,
+
+    /// The missing block.
+    pub target: u16,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+/// A serializable set of changes that turns one building into another.
+///
+/// Patches are computed with [`Patch::compute`], which matches blocks by
+/// position and id (see [`match_buildings`]). Roots and blocks of the base
+/// building are addressed by index, new ones by their position in the
+/// `added_*` lists, and every reference is stored as a [`Ref`] so that it can
+/// be remapped when the patch is applied.
+pub struct Patch {
+    /// Indices of removed base roots.
+    pub removed_roots: Vec<u16>,
+
+    /// New values of changed base roots.
+    pub changed_roots: Vec<(u16, Root)>,
+
+    /// Roots appended after the kept base roots.
+    pub added_roots: Vec<Root>,
+
+    /// Indices of removed base blocks.
+    pub removed_blocks: Vec<u16>,
+
+    /// Changed fields of base blocks.
+    pub changed_blocks: Vec<(u16, Vec<BlockField>)>,
+
+    /// Blocks appended after the kept base blocks, as full field lists.
+    pub added_blocks: Vec<Vec<BlockField>>,
+}
+
+impl Patch {
+    /// Computes the patch that turns `base` into `new`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    /// use sw_structure_io::patch::Patch;
+    ///
+    /// let base = Building::default();
+    /// let mut new = Building::default();
+    /// new.roots.push(Root::default());
+    /// new.blocks.push(Block::default());
+    ///
+    /// let patch = Patch::compute(&base, &new);
+    /// let mut patched = base.clone();
+    /// patch.apply(&mut patched).unwrap();
+    /// assert_eq!(patched, new);
+    /// ```
+    pub fn compute(base: &Building, new: &Building) -> Patch {
+        Self::compute_with_dangling(base, new).0
+    }
+
+    /// Computes the patch that turns `base` into `new`, like
+    /// [`Patch::compute`], and reports the references of `new` to blocks it
+    /// does not have. They can not be expressed as a [`Ref`] and are left out
+    /// of the patch.
+    pub fn compute_with_dangling(base: &Building, new: &Building) -> (Patch, Vec<DanglingReference>) {
+        let matching = match_buildings(base, new);
+        let base_of_root = matching.new_roots(new.roots.len());
+        let base_of_block = matching.new_blocks(new.blocks.len());
+
+        let mut patch = Patch::default();
+        let mut dangling = Vec::new();
+
+        // Position of every unmatched new root/block in the `added_*` lists.
+        let added_index = |base_of: &[Option<usize>]| {
+            let mut count = 0u16;
+            base_of
+                .iter()
+                .map(|b| b.is_none().then(|| { count += 1; count - 1 }))
+                .collect::<Vec<Option<u16>>>()
+        };
+        let added_roots = added_index(&base_of_root);
+        let added_blocks = added_index(&base_of_block);
+
+        let root_ref = |i: u16| match (base_of_root.get(i as usize), added_roots.get(i as usize)) {
+            (Some(Some(b)), _) => Ref::Base(*b as u16),
+            (_, Some(Some(a))) => Ref::Added(*a),
+            // Dangling in `new` as well, keep the raw value.
+            _ => Ref::Base(i),
+        };
+        let block_ref = |i: u16| match (base_of_block.get(i as usize), added_blocks.get(i as usize)) {
+            (Some(Some(b)), _) => Some(Ref::Base(*b as u16)),
+            (_, Some(Some(a))) => Some(Ref::Added(*a)),
+            _ => None,
+        };
+
+        for (index, matched) in matching.roots.iter().enumerate() {
+            match matched {
+                None => patch.removed_roots.push(index as u16),
+                Some(n) if new.roots[*n] != base.roots[index] => {
+                    patch.changed_roots.push((index as u16, new.roots[*n].clone()));
+                }
+                Some(_) => {}
+            }
+        }
+        for (index, root) in new.roots.iter().enumerate() {
+            if base_of_root[index].is_none() {
+                patch.added_roots.push(root.clone());
+            }
+        }
+
+        for (index, matched) in matching.blocks.iter().enumerate() {
+            let Some(n) = *matched else {
+                patch.removed_blocks.push(index as u16);
+                continue;
+            };
+            let old = describe(&base.blocks[index], |i| Some(Ref::Base(i)), Ref::Base, &mut Vec::new());
+            let mut missing = Vec::new();
+            let changed: Vec<BlockField> = describe(&new.blocks[n], block_ref, root_ref, &mut missing)
+                .into_iter()
+                .zip(old)
+                .filter(|(new, old)| new != old)
+                .map(|(new, _)| new)
+                .collect();
+            if !changed.is_empty() {
+                patch.changed_blocks.push((index as u16, changed));
+            }
+            dangling.extend(missing.into_iter().map(|target| DanglingReference { block: n as u16, target }));
+        }
+        for (index, block) in new.blocks.iter().enumerate() {
+            if base_of_block[index].is_none() {
+                let mut missing = Vec::new();
+                patch.added_blocks.push(describe(block, block_ref, root_ref, &mut missing));
+                dangling.extend(missing.into_iter().map(|target| DanglingReference { block: index as u16, target }));
+            }
+        }
+        dangling.sort_by_key(|reference| reference.block);
+
+        (patch, dangling)
+    }
+
+    /// Returns `true` if the patch does not change anything.
+    pub fn is_empty(&self) -> bool {
+        *self == Patch::default()
+    }
+
+    /// Applies the patch to a building.
+    ///
+    /// Kept blocks are renumbered, and their references to removed blocks
+    /// are deleted. The building is left untouched if an error is returned.
+    ///
+    /// # Errors
+    /// Returns an error if the patch references roots or blocks that are not
+    /// in the building, or that the patch itself removes.
+    pub fn apply(&self, building: &mut Building) -> Result<(), PatchError> {
+        let root_map = keep_map(building.roots.len(), &self.removed_roots)
+            .map_err(|root| PatchError::RootOutOfRange { root })?;
+        let block_map = keep_map(building.blocks.len(), &self.removed_blocks)
+            .map_err(|block| PatchError::BlockOutOfRange { block })?;
+        let kept_roots = root_map.iter().flatten().count();
+        let kept_blocks = block_map.iter().flatten().count();
+
+        if kept_roots + self.added_roots.len() > u16::MAX as usize
+            || kept_blocks + self.added_blocks.len() > u16::MAX as usize
+        {
+            return Err(PatchError::TooManyValues);
+        }
+
+        let resolve_root = |r: Ref| match r {
+            Ref::Base(i) => root_map
+                .get(i as usize)
+                .ok_or(PatchError::RootOutOfRange { root: i })?
+                .ok_or(PatchError::RemovedRoot { root: i }),
+            Ref::Added(i) if (i as usize) < self.added_roots.len() => Ok((kept_roots + i as usize) as u16),
+            Ref::Added(index) => Err(PatchError::AddedOutOfRange { index, count: self.added_roots.len() }),
+        };
+        let resolve_block = |r: Ref| match r {
+            Ref::Base(i) => block_map
+                .get(i as usize)
+                .ok_or(PatchError::BlockOutOfRange { block: i })?
+                .ok_or(PatchError::RemovedBlock { block: i }),
+            Ref::Added(i) if (i as usize) < self.added_blocks.len() => Ok((kept_blocks + i as usize) as u16),
+            Ref::Added(index) => Err(PatchError::AddedOutOfRange { index, count: self.added_blocks.len() }),
+        };
+
+        let mut roots = building.roots.clone();
+        for (index, root) in self.changed_roots.iter() {
+            *roots.get_mut(*index as usize).ok_or(PatchError::RootOutOfRange { root: *index })? = root.clone();
+        }
+
+        let mut blocks = building.blocks.clone();
+        let mut changed = vec![false; blocks.len()];
+        let mut reassigned = vec![false; blocks.len()];
+        for (index, fields) in self.changed_blocks.iter() {
+            let block = blocks.get_mut(*index as usize).ok_or(PatchError::BlockOutOfRange { block: *index })?;
+            changed[*index as usize] = true;
+            // Fields of the base block still hold base indices, the patch values are resolved.
+            block.remap_references(|i| block_map.get(i as usize).copied().unwrap_or(Some(i)));
+            for field in fields {
+                reassigned[*index as usize] |= matches!(field, BlockField::Root(_));
+                set_field(block, field, &resolve_block, &resolve_root)?;
+            }
+        }
+        for (index, block) in blocks.iter_mut().enumerate() {
+            if block_map[index].is_none() {
+                continue;
+            }
+            if !changed[index] {
+                block.remap_references(|i| block_map.get(i as usize).copied().unwrap_or(Some(i)));
+            }
+            if !reassigned[index] && (block.root as usize) < root_map.len() {
+                block.root = resolve_root(Ref::Base(block.root))?;
+            }
+        }
+
+        let mut added = Vec::with_capacity(self.added_blocks.len());
+        for fields in self.added_blocks.iter() {
+            let mut block = Block::default();
+            for field in fields {
+                set_field(&mut block, field, &resolve_block, &resolve_root)?;
+            }
+            added.push(block);
+        }
+
+        building.roots = roots
+            .into_iter()
+            .zip(root_map.iter())
+            .filter_map(|(root, kept)| kept.map(|_| root))
+            .chain(self.added_roots.iter().cloned())
+            .collect();
+        building.blocks = blocks
+            .into_iter()
+            .zip(block_map.iter())
+            .filter_map(|(block, kept)| kept.map(|_| block))
+            .chain(added)
+            .collect();
+
+        Ok(())
+    }
+}
+
+/// Maps every index to its position after removing `removed`, or `None` if it is removed.
+///
+/// Returns the first out-of-range index as error.
+fn keep_map(len: usize, removed: &[u16]) -> Result<Vec<Option<u16>>, u16> {
+    let mut kept = vec![true; len];
+    for &index in removed {
+        *kept.get_mut(index as usize).ok_or(index)? = false;
+    }
+    let mut next = 0u16;
+    Ok(kept
+        .into_iter()
+        .map(|k| k.then(|| { next += 1; next - 1 }))
+        .collect())
+}
+
+fn set_field(
+    block: &mut Block,
+    field: &BlockField,
+    resolve_block: &impl Fn(Ref) -> Result<u16, PatchError>,
+    resolve_root: &impl Fn(Ref) -> Result<u16, PatchError>,
+) -> Result<(), PatchError> {
+    match field {
+        BlockField::Position(v) => block.position = *v,
+        BlockField::Rotation(v) => block.rotation = *v,
+        BlockField::Id(v) => block.id = *v,
+        BlockField::Root(r) => block.root = resolve_root(*r)?,
+        BlockField::Name(v) => block.name = v.clone(),
+        BlockField::EnableState(v) => block.enable_state = *v,
+        BlockField::EnableStateCurrent(v) => block.enable_state_current = *v,
+        BlockField::Connections(refs) => {
+            block.connections = refs.iter().map(|&r| resolve_block(r)).collect::<Result<_, _>>()?;
+        }
+        BlockField::Load(r) => block.load = r.map(resolve_block).transpose()?,
+        BlockField::Color(v) => block.color = *v,
+        BlockField::Metadata { metadata, fields, incoming_connections_order } => {
+            let mut metadata = metadata.as_deref().cloned();
+            if let Some(metadata) = &mut metadata {
+                metadata.fields = fields
+                    .iter()
+                    .map(|items| items.iter().map(|&r| resolve_block(r)).collect())
+                    .collect::<Result<_, _>>()?;
+                if let TypeSettings::MathBlock { incoming_connections_order: order, .. } = &mut metadata.type_settings {
+                    *order = incoming_connections_order
+                        .iter()
+                        .map(|&r| resolve_block(r).and_then(|i| u8::try_from(i).map_err(|_| PatchError::TooManyValues)))
+                        .collect::<Result<_, _>>()?;
+                }
+            }
+            block.metadata = metadata;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A disagreement between the two sides of a [`merge`].
+///
+/// Conflicts are always resolved in favour of "ours"; they are reported so
+/// that the result can be reviewed.
+pub enum Conflict {
+    /// Both sides changed the same base root differently.
+    RootChanged { root: u16 },
+
+    /// One side removed a base root the other changed.
+    RootRemovedAndChanged { root: u16 },
+
+    /// One side removed a base root the other still assigns blocks to.
+    /// The root is kept.
+    RootRemovedAndUsed { root: u16 },
+
+    /// Both sides changed the same field of a base block differently.
+    BlockChanged { block: u16, field: &'static str },
+
+    /// One side removed a base block the other changed.
+    BlockRemovedAndChanged { block: u16 },
+
+    /// One side references a base block the other removed.
+    /// The reference is dropped.
+    BlockRemovedAndReferenced { block: u16 },
+
+    /// One side references a block it does not have, see
+    /// [`Patch::compute_with_dangling`]. Both indices are in that side's
+    /// building. The reference is dropped.
+    DanglingReference { block: u16, target: u16 },
+}
+
+#[derive(Clone, Debug, Default)]
+/// Result of a three-way [`merge`].
+pub struct Merge {
+    pub building: Building,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Merges the changes of `ours` and `theirs` relative to their common `base`.
+///
+/// Both sides are turned into patches with [`Patch::compute`] and combined
+/// field by field, down to the fields of `Metadata`: changes made by only
+/// one side are taken, identical changes are taken once, and everything else
+/// is reported as a [`Conflict`] and resolved in favour of `ours`. Roots and
+/// blocks added by both sides are all kept, ours first.
+///
+/// # Errors
+/// Returns an error if the combined patch can not be applied to `base`,
+/// which only happens when `base` itself has dangling references.
+pub fn merge(base: &Building, ours: &Building, theirs: &Building) -> Result<Merge, PatchError> {
+    let (ours, ours_dangling) = Patch::compute_with_dangling(base, ours);
+    let (mut theirs, theirs_dangling) = Patch::compute_with_dangling(base, theirs);
+    let mut conflicts: Vec<Conflict> = ours_dangling
+        .into_iter()
+        .chain(theirs_dangling)
+        .map(|DanglingReference { block, target }| Conflict::DanglingReference { block, target })
+        .collect();
+
+    // Added items of theirs come after ours.
+    let root_offset = ours.added_roots.len() as u16;
+    let block_offset = ours.added_blocks.len() as u16;
+    for fields in theirs.changed_blocks.iter_mut().map(|(_, f)| f).chain(theirs.added_blocks.iter_mut()) {
+        for field in fields.iter_mut() {
+            if let BlockField::Root(Ref::Added(i)) = field {
+                *i += root_offset;
+            }
+            for r in field.refs_mut() {
+                if let Ref::Added(i) = r {
+                    *i += block_offset;
+                }
+            }
+        }
+    }
+
+    let mut merged = Patch {
+        added_roots: [ours.added_roots.clone(), theirs.added_roots].concat(),
+        ..Default::default()
+    };
+
+    // Removals are tracked as sets, and listed in the order of the patches
+    // at the end.
+    let ours_removed_roots: HashSet<u16> = ours.removed_roots.iter().copied().collect();
+    let theirs_removed_roots: HashSet<u16> = theirs.removed_roots.iter().copied().collect();
+    let ours_removed_blocks: HashSet<u16> = ours.removed_blocks.iter().copied().collect();
+    let theirs_removed_blocks: HashSet<u16> = theirs.removed_blocks.iter().copied().collect();
+
+    // Roots
+    let mut removed_roots: HashSet<u16> = &ours_removed_roots | &theirs_removed_roots;
+    merged.changed_roots = ours.changed_roots.clone();
+    for (root, value) in theirs.changed_roots {
+        match ours.changed_roots.iter().find(|(r, _)| *r == root) {
+            Some((_, ours)) if *ours != value => conflicts.push(Conflict::RootChanged { root }),
+            Some(_) => {}
+            None if ours_removed_roots.contains(&root) => conflicts.push(Conflict::RootRemovedAndChanged { root }),
+            None => merged.changed_roots.push((root, value)),
+        }
+    }
+    for (root, _) in ours.changed_roots.iter() {
+        if theirs_removed_roots.contains(root) {
+            conflicts.push(Conflict::RootRemovedAndChanged { root: *root });
+            removed_roots.remove(root);
+        }
+    }
+
+    // Blocks
+    let mut removed_blocks: HashSet<u16> = &ours_removed_blocks | &theirs_removed_blocks;
+    // Position of every block in `merged.changed_blocks`.
+    let mut changed: HashMap<u16, usize> = HashMap::new();
+    for (block, fields) in ours.changed_blocks.iter() {
+        if theirs_removed_blocks.contains(block) {
+            conflicts.push(Conflict::BlockRemovedAndChanged { block: *block });
+            removed_blocks.remove(block);
+        }
+        changed.insert(*block, merged.changed_blocks.len());
+        merged.changed_blocks.push((*block, fields.clone()));
+    }
+    for (block, fields) in theirs.changed_blocks {
+        if ours_removed_blocks.contains(&block) {
+            conflicts.push(Conflict::BlockRemovedAndChanged { block });
+            continue;
+        }
+        let index = *changed.entry(block).or_insert_with(|| {
+            merged.changed_blocks.push((block, Vec::new()));
+            merged.changed_blocks.len() - 1
+        });
+        let ours_fields = &mut merged.changed_blocks[index].1;
+        for field in fields {
+            match ours_fields.iter_mut().find(|f| discriminant(&**f) == discriminant(&field)) {
+                Some(ours) if *ours != field => {
+                    let base_field = base.blocks.get(block as usize).and_then(|b| {
+                        describe(b, |i| Some(Ref::Base(i)), Ref::Base, &mut Vec::new())
+                            .into_iter()
+                            .find(|f| discriminant(f) == discriminant(&field))
+                    });
+                    match base_field.and_then(|base| merge_metadata(&base, ours, &field)) {
+                        Some((merged, fields)) => {
+                            *ours = merged;
+                            conflicts.extend(fields.into_iter().map(|field| Conflict::BlockChanged { block, field }));
+                        }
+                        None => conflicts.push(Conflict::BlockChanged { block, field: field.name() }),
+                    }
+                }
+                Some(_) => {}
+                None => ours_fields.push(field),
+            }
+        }
+    }
+    merged.added_blocks = [ours.added_blocks, theirs.added_blocks].concat();
+
+    // References to removed roots keep the root, references to removed blocks are dropped.
+    for fields in merged.changed_blocks.iter_mut().map(|(_, f)| f).chain(merged.added_blocks.iter_mut()) {
+        for field in fields.iter_mut() {
+            if let BlockField::Root(Ref::Base(root)) = field && removed_roots.remove(root) {
+                conflicts.push(Conflict::RootRemovedAndUsed { root: *root });
+            }
+            let mut dropped = Vec::new();
+            field.retain_refs(|r| match r {
+                Ref::Base(block) if removed_blocks.contains(block) => {
+                    dropped.push(*block);
+                    false
+                }
+                _ => true,
+            });
+            conflicts.extend(dropped.into_iter().map(|block| Conflict::BlockRemovedAndReferenced { block }));
+        }
+    }
+    for (index, block) in base.blocks.iter().enumerate() {
+        let index = index as u16;
+        let reassigned = changed
+            .get(&index)
+            .is_some_and(|&i| merged.changed_blocks[i].1.iter().any(|f| matches!(f, BlockField::Root(_))));
+        if !removed_blocks.contains(&index) && !reassigned && removed_roots.remove(&block.root) {
+            conflicts.push(Conflict::RootRemovedAndUsed { root: block.root });
+        }
+    }
+
+    merged.removed_roots = ordered(&ours.removed_roots, &theirs.removed_roots, &removed_roots);
+    merged.removed_blocks = ordered(&ours.removed_blocks, &theirs.removed_blocks, &removed_blocks);
+
+    let mut building = base.clone();
+    merged.apply(&mut building)?;
+
+    Ok(Merge { building, conflicts })
+}
+
+/// The items of `ours` then `theirs` that are still in `kept`, each once.
+fn ordered(ours: &[u16], theirs: &[u16], kept: &HashSet<u16>) -> Vec<u16> {
+    let mut seen = HashSet::new();
+    ours.iter().chain(theirs).copied().filter(|i| kept.contains(i) && seen.insert(*i)).collect()
+}
+
+/// Merges the metadata both sides of a [`merge`] changed, field by field.
+///
+/// Returns the merged value and the fields both sides changed differently,
+/// taken from `ours`. `None` if the values are not metadata, or if any side
+/// has none.
+fn merge_metadata(base: &BlockField, ours: &BlockField, theirs: &BlockField) -> Option<(BlockField, Vec<&'static str>)> {
+    let (
+        BlockField::Metadata { metadata: Some(base), fields: base_fields, incoming_connections_order: base_order },
+        BlockField::Metadata { metadata: Some(ours), fields: ours_fields, incoming_connections_order: ours_order },
+        BlockField::Metadata { metadata: Some(theirs), fields: theirs_fields, incoming_connections_order: theirs_order },
+    ) = (base, ours, theirs) else {
+        return None;
+    };
+
+    let mut conflicts = Vec::new();
+    let fields = pick("metadata.fields", base_fields, ours_fields, theirs_fields, &mut conflicts);
+    // The math block ordering and its slots are merged as one.
+    let (type_settings, incoming_connections_order) = pick(
+        "metadata.type_settings",
+        &(&base.type_settings, base_order),
+        &(&ours.type_settings, ours_order),
+        &(&theirs.type_settings, theirs_order),
+        &mut conflicts,
+    );
+    let metadata = Metadata {
+        toggles: pick("metadata.toggles", &base.toggles, &ours.toggles, &theirs.toggles, &mut conflicts),
+        values: pick("metadata.values", &base.values, &ours.values, &theirs.values, &mut conflicts),
+        // The references are kept apart, in `fields`.
+        fields: vec![Vec::new(); fields.len()],
+        dropdowns: pick("metadata.dropdowns", &base.dropdowns, &ours.dropdowns, &theirs.dropdowns, &mut conflicts),
+        colors: pick("metadata.colors", &base.colors, &ours.colors, &theirs.colors, &mut conflicts),
+        gradients: pick("metadata.gradients", &base.gradients, &ours.gradients, &theirs.gradients, &mut conflicts),
+        vectors: pick("metadata.vectors", &base.vectors, &ours.vectors, &theirs.vectors, &mut conflicts),
+        type_settings: type_settings.clone(),
+    };
+
+    let metadata = Some(Box::new(metadata));
+    let incoming_connections_order = incoming_connections_order.clone();
+    Some((BlockField::Metadata { metadata, fields, incoming_connections_order }, conflicts))
+}
+
+/// Three-way pick of a single value: the side that changed it, or `ours` if
+/// both did, reporting `path` if they disagree.
+fn pick<T: PartialEq + Clone>(path: &'static str, base: &T, ours: &T, theirs: &T, conflicts: &mut Vec<&'static str>) -> T {
+    if ours == base {
+        theirs.clone()
+    } else {
+        if theirs != base && theirs != ours {
+            conflicts.push(path);
+        }
+        ours.clone()
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use crate::bounds::Bounds;
+use crate::edit::Remap;
+use crate::id::BlockId;
+use crate::structs::*;
+
+/// A grid cell, as integer coordinates of its minimum corner in cell units.
+pub type Cell = [i32; 3];
+
+#[derive(Clone, Debug, Default)]
+/// A hash grid over the block positions of a building, for neighbour and
+/// overlap queries without scanning every block.
+///
+/// The index is built from a building and does not borrow it. When the
+/// building is edited, the index is kept consistent by replaying the edit:
+/// [`SpatialIndex::apply_remap`] with the [`Remap`] returned by the editing
+/// APIs, then [`SpatialIndex::insert`] for inserted blocks and
+/// [`SpatialIndex::update`] for moved ones.
+///
+/// # Example
+/// ```rust
+/// use sw_structure_io::structs::*;
+/// use sw_structure_io::id::BlockId;
+/// use sw_structure_io::spatial::SpatialIndex;
+///
+/// let mut building = Building::default();
+/// building.roots.push(Root::default());
+/// for x in 0..4 {
+///     building.blocks.push(Block { position: [x as f32, 0.0, 0.0], ..Default::default() });
+/// }
+///
+/// let mut index = SpatialIndex::new(&building, 1.0);
+/// assert_eq!(index.nearest([2.2, 0.0, 0.0], 2), [BlockId(2), BlockId(3)]);
+///
+/// let removal = building.remove_blocks([BlockId(2)]);
+/// index.apply_remap(&removal.remap);
+/// assert_eq!(index.nearest([2.2, 0.0, 0.0], 1), [BlockId(2)]);
+/// ```
+pub struct SpatialIndex {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<BlockId>>,
+    positions: Vec<Option<[f32; 3]>>,
+}
+
+impl SpatialIndex {
+    /// Indexes every block of `building` in cells of `cell_size`.
+    ///
+    /// A cell size around the spacing of blocks (e.g. `1.0`) works best.
+    ///
+    /// # Panics
+    /// Panics if `cell_size` is not positive.
+    pub fn new(building: &Building, cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "cell size (is {cell_size}) should be positive");
+
+        let mut index = Self {
+            cell_size,
+            cells: HashMap::new(),
+            positions: building.iter_blocks().map(|(_, block)| Some(block.position)).collect(),
+        };
+        index.rebuild_cells();
+        index
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Number of indexed blocks.
+    pub fn len(&self) -> usize {
+        self.positions.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Cell containing `position`.
+    pub fn cell_of(&self, position: [f32; 3]) -> Cell {
+        cell_of(position, self.cell_size)
+    }
+
+    /// Blocks in a single cell, in insertion order.
+    pub fn in_cell(&self, cell: Cell) -> &[BlockId] {
+        self.cells.get(&cell).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Blocks whose position lies inside `bounds`, borders included, sorted
+    /// by handle.
+    pub fn in_box(&self, bounds: &Bounds) -> Vec<BlockId> {
+        if bounds.is_empty() {
+            return Vec::new();
+        }
+        let (min, max) = (self.cell_of(bounds.min), self.cell_of(bounds.max));
+
+        let mut found: Vec<BlockId> = Vec::new();
+        // Cells saturate at the `i32` range, so count them in `i64`.
+        let box_cells = (0..3)
+            .map(|i| (i64::from(max[i]) - i64::from(min[i]) + 1) as u64)
+            .fold(1u64, u64::saturating_mul);
+        if box_cells > self.cells.len() as u64 {
+            // Visiting occupied cells is cheaper than visiting the box.
+            for (cell, blocks) in self.cells.iter() {
+                if (0..3).all(|i| min[i] <= cell[i] && cell[i] <= max[i]) {
+                    found.extend(blocks.iter().filter(|&&b| self.position_in(b, bounds)));
+                }
+            }
+        } else {
+            for x in min[0]..=max[0] {
+                for y in min[1]..=max[1] {
+                    for z in min[2]..=max[2] {
+                        found.extend(self.in_cell([x, y, z]).iter().filter(|&&b| self.position_in(b, bounds)));
+                    }
+                }
+            }
+        }
+        found.sort();
+        found
+    }
+
+    /// Up to `k` blocks closest to `point`, nearest first. Blocks at the
+    /// same distance are ordered by handle.
+    pub fn nearest(&self, point: [f32; 3], k: usize) -> Vec<BlockId> {
+        if k == 0 || self.cells.is_empty() {
+            return Vec::new();
+        }
+
+        let center = self.cell_of(point);
+        let (lowest, highest) = self.occupied_range();
+        // Past this radius, the shell around `center` covers every occupied cell.
+        let max_radius = (0..3)
+            .map(|i| {
+                let (center, lowest, highest) = (i64::from(center[i]), i64::from(lowest[i]), i64::from(highest[i]));
+                (center - lowest).abs().max((highest - center).abs())
+            })
+            .max()
+            .unwrap_or(0);
+
+        let mut candidates: Vec<(f32, BlockId)> = Vec::new();
+        for radius in 0..=max_radius {
+            if shell_size(radius) > self.cells.len() as u64 {
+                // The shells grew larger than the occupied cells: scan those instead.
+                candidates = self.cells.values().flatten().map(|&b| (self.distance(b, point), b)).collect();
+                break;
+            }
+            for_shell(center, radius, |cell| {
+                candidates.extend(self.in_cell(cell).iter().map(|&b| (self.distance(b, point), b)));
+            });
+
+            // Every block outside the visited cells is at least this far away.
+            let reach = radius as f32 * self.cell_size;
+            if candidates.len() >= k {
+                candidates.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+                if candidates[k - 1].0 <= reach {
+                    break;
+                }
+            }
+        }
+
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        candidates.into_iter().take(k).map(|(_, b)| b).collect()
+    }
+
+    /// Position of an indexed block.
+    pub fn position(&self, block: BlockId) -> Option<[f32; 3]> {
+        self.positions.get(block.index()).copied().flatten()
+    }
+
+    /// Renumbers the index after an edit returned `remap`, dropping removed
+    /// blocks.
+    ///
+    /// Blocks inserted by the edit are not known to the index; add them with
+    /// [`SpatialIndex::insert`].
+    pub fn apply_remap(&mut self, remap: &Remap) {
+        let len = remap.as_slice().iter().flatten().map(|&new| new + 1).max().unwrap_or(0);
+        let mut positions = vec![None; len];
+        for (old, new) in remap.as_slice().iter().enumerate() {
+            if let (Some(new), Some(position)) = (new, self.positions.get(old)) {
+                positions[*new] = *position;
+            }
+        }
+        self.positions = positions;
+        self.rebuild_cells();
+    }
+
+    /// Adds a block, or moves it if it is already indexed.
+    pub fn insert(&mut self, block: BlockId, position: [f32; 3]) {
+        self.remove(block);
+        if self.positions.len() <= block.index() {
+            self.positions.resize(block.index() + 1, None);
+        }
+        self.positions[block.index()] = Some(position);
+        let cell = self.cell_of(position);
+        self.cells.entry(cell).or_default().push(block);
+    }
+
+    /// Moves an indexed block to `position`.
+    pub fn update(&mut self, block: BlockId, position: [f32; 3]) {
+        self.insert(block, position);
+    }
+
+    /// Swaps two blocks, mirroring [`Building::swap_blocks`].
+    pub fn swap(&mut self, a: BlockId, b: BlockId) {
+        let (position_a, position_b) = (self.position(a), self.position(b));
+        self.remove(a);
+        self.remove(b);
+        if let Some(position) = position_b {
+            self.insert(a, position);
+        }
+        if let Some(position) = position_a {
+            self.insert(b, position);
+        }
+    }
+
+    /// Removes a block from the index without renumbering the others.
+    pub fn remove(&mut self, block: BlockId) {
+        let Some(position) = self.positions.get_mut(block.index()).and_then(Option::take) else {
+            return;
+        };
+        let cell = self.cell_of(position);
+        if let Some(blocks) = self.cells.get_mut(&cell) {
+            blocks.retain(|&b| b != block);
+            if blocks.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    fn rebuild_cells(&mut self) {
+        self.cells.clear();
+        for (index, position) in self.positions.iter().enumerate() {
+            if let Some(position) = position {
+                self.cells.entry(cell_of(*position, self.cell_size)).or_default().push(BlockId(index as u16));
+            }
+        }
+    }
+
+    fn occupied_range(&self) -> (Cell, Cell) {
+        let mut lowest = [i32::MAX; 3];
+        let mut highest = [i32::MIN; 3];
+        for cell in self.cells.keys() {
+            for i in 0..3 {
+                lowest[i] = lowest[i].min(cell[i]);
+                highest[i] = highest[i].max(cell[i]);
+            }
+        }
+        (lowest, highest)
+    }
+
+    fn position_in(&self, block: BlockId, bounds: &Bounds) -> bool {
+        self.position(block).is_some_and(|p| bounds.contains(&p))
+    }
+
+    fn distance(&self, block: BlockId, point: [f32; 3]) -> f32 {
+        let position = self.position(block).unwrap_or([f32::INFINITY; 3]);
+        (0..3).map(|i| (position[i] - point[i]).powi(2)).sum::<f32>().sqrt()
+    }
+}
+
+fn cell_of(position: [f32; 3], cell_size: f32) -> Cell {
+    position.map(|v| (v / cell_size).floor() as i32)
+}
+
+/// Number of cells at Chebyshev distance `radius` from a cell.
+fn shell_size(radius: i64) -> u64 {
+    let cube = |side: u64| side.saturating_mul(side).saturating_mul(side);
+    let outer = cube(2 * radius as u64 + 1);
+    let inner = if radius == 0 { 0 } else { cube(2 * radius as u64 - 1) };
+    outer.saturating_sub(inner)
+}
+
+/// Calls `f` for every cell at Chebyshev distance `radius` from `center`.
+///
+/// Cells outside the `i32` range can not hold blocks, and are skipped.
+fn for_shell(center: Cell, radius: i64, mut f: impl FnMut(Cell)) {
+    let offset = |axis: usize, by: i64| i32::try_from(i64::from(center[axis]) + by).ok();
+    for x in -radius..=radius {
+        for y in -radius..=radius {
+            let on_side = x.abs() == radius || y.abs() == radius;
+            // Inside the shell, only the two caps along Z are visited.
+            let step = if on_side || radius == 0 { 1 } else { (2 * radius) as usize };
+            for z in (-radius..=radius).step_by(step) {
+                if let (Some(x), Some(y), Some(z)) = (offset(0, x), offset(1, y), offset(2, z)) {
+                    f([x, y, z]);
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,6 @@
-#[derive(Debug, Default)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 /// Represents an entire assembled structure.
 /// 
 /// A `Building` is composed of one or more roots (rigid bodies) and a flat list
@@ -15,7 +17,7 @@ pub struct Building {
     pub blocks: Vec<Block>,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 /// A physically independent part of a building.
 /// 
 /// A `Root` is a rigid body that can contain multiple blocks.  
@@ -31,7 +33,7 @@ pub struct Root {
     pub rotation: [f32; 3],
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 /// A single element in a building.
 ///
 /// Every `Block` is **always part of a `Root`**, and its `root` field
@@ -79,7 +81,7 @@ pub struct Block {
     pub color: Option<[u8; 4]>,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 /// A color gradient consisting of color and alpha keys.
 /// 
 /// Each gradient is defined by color values over normalized time and alpha
@@ -91,7 +93,7 @@ pub struct Gradient {
     pub alpha_time_keys: Vec<f32>,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 /// All per-block editable settings.
 /// 
 /// `Metadata` contains a variety of UI-driven values used by different block
@@ -122,7 +124,7 @@ pub struct Metadata {
     pub type_settings: TypeSettings,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 /// Additional metadata specific to certain block types.
 ///
 /// `TypeSettings` defines extra configuration for a block based on its type (`id`).
@@ -0,0 +1,669 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::adjacency::SizeTable;
+use crate::bounds::Bounds;
+use crate::hierarchy::RootGraph;
+use crate::id::{BlockId, RootId};
+use crate::mass::{MassProperties, MassTable};
+use crate::math;
+use crate::structs::*;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+/// A rigid transform: a rotation around the origin followed by a translation.
+///
+/// `rotation` uses the same Euler angles as `Block::rotation` and
+/// `Root::rotation` (degrees, applied around Z, then X, then Y).
+pub struct Transform {
+    /// Offset added after rotating.
+    pub translation: [f32; 3],
+
+    /// Rotation around the origin, as Euler angles in degrees.
+    pub rotation: [f32; 3],
+}
+
+impl Transform {
+    /// A transform that only moves by `translation`.
+    pub fn from_translation(translation: [f32; 3]) -> Self {
+        Self { translation, ..Default::default() }
+    }
+
+    /// A transform that only rotates around the origin.
+    pub fn from_rotation(rotation: [f32; 3]) -> Self {
+        Self { rotation, ..Default::default() }
+    }
+
+    /// Returns `true` if the transform does not rotate.
+    pub fn is_translation(&self) -> bool {
+        self.rotation == [0.0; 3]
+    }
+
+    /// Transforms a world-space position.
+    pub fn apply_position(&self, position: [f32; 3]) -> [f32; 3] {
+        let rotated = if self.is_translation() {
+            position
+        } else {
+            math::rotate(math::from_euler(self.rotation), position)
+        };
+        [0, 1, 2].map(|i| rotated[i] + self.translation[i])
+    }
+
+    /// Transforms a world-space rotation.
+    ///
+    /// Rotations are returned unchanged by pure translations; otherwise the
+    /// result is normalized to `[0, 360)` degrees.
+    pub fn apply_rotation(&self, rotation: [f32; 3]) -> [f32; 3] {
+        if self.is_translation() {
+            return rotation;
+        }
+        math::compose_euler(rotation, self.rotation)
+    }
+
+    /// Transform applying `self`, then `next`.
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            translation: next.apply_position(self.translation),
+            rotation: next.apply_rotation(self.rotation),
+        }
+    }
+
+    /// A rotation by `rotation` around `pivot` instead of the origin.
+    pub fn rotation_around(rotation: [f32; 3], pivot: [f32; 3]) -> Self {
+        let rotated_pivot = Transform::from_rotation(rotation).apply_position(pivot);
+        Transform {
+            translation: [0, 1, 2].map(|i| pivot[i] - rotated_pivot[i]),
+            rotation,
+        }
+    }
+
+    /// Transform undoing `self`.
+    pub fn inverse(&self) -> Transform {
+        let rotation = math::inverse(math::from_euler(self.rotation));
+        let translation = math::rotate(rotation, self.translation).map(|v| math::positive_zero(-v));
+        Transform { translation, rotation: math::to_euler(rotation) }
+    }
+
+    /// Expresses `self` in the local space of `parent`.
+    ///
+    /// For a block and its root, this is the block transform relative to the
+    /// root: `relative.then(parent)` gives back `self`.
+    pub fn relative_to(&self, parent: &Transform) -> Transform {
+        self.then(&parent.inverse())
+    }
+}
+
+impl Root {
+    /// World-space transform of the root.
+    pub fn transform(&self) -> Transform {
+        Transform { translation: self.position, rotation: self.rotation }
+    }
+}
+
+impl Block {
+    /// World-space transform of the block.
+    pub fn transform(&self) -> Transform {
+        Transform { translation: self.position, rotation: self.rotation }
+    }
+}
+
+impl Building {
+    /// Transform of a block relative to its root.
+    ///
+    /// Returns `None` if the block or its root do not exist.
+    pub fn block_to_root(&self, block: BlockId) -> Option<Transform> {
+        let root = self.root_of(block)?;
+        Some(self.block(block)?.transform().relative_to(&root.transform()))
+    }
+
+    /// Sets the world transform of a block from its transform relative to
+    /// its root, the inverse of [`Building::block_to_root`].
+    ///
+    /// Returns `false`, leaving the block unchanged, if the block or its root
+    /// do not exist.
+    pub fn set_block_local(&mut self, block: BlockId, local: &Transform) -> bool {
+        let Some(root) = self.root_of(block).map(Root::transform) else {
+            return false;
+        };
+        let world = local.then(&root);
+        let block = &mut self.blocks[block.index()];
+        block.position = world.translation;
+        block.rotation = world.rotation;
+        true
+    }
+
+    /// Transforms of the blocks of a root, relative to the root.
+    pub fn blocks_local(&self, root: RootId) -> Vec<(BlockId, Transform)> {
+        let Some(root_transform) = self.root(root).map(Root::transform) else {
+            return Vec::new();
+        };
+        self.blocks_of(root)
+            .map(|(id, block)| (id, block.transform().relative_to(&root_transform)))
+            .collect()
+    }
+
+    /// Moves and rotates a root by `transform`, carrying its blocks along so
+    /// their transforms relative to the root are kept.
+    ///
+    /// Roots carried by this one through `load` are not moved, see
+    /// [`Building::transform_subassembly`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    /// use sw_structure_io::id::RootId;
+    /// use sw_structure_io::transform::Transform;
+    ///
+    /// let mut building = Building::default();
+    /// // An arm hanging from a bearing at the origin.
+    /// building.roots.push(Root::default());
+    /// building.blocks.push(Block { position: [0.0, 0.0, 2.0], ..Default::default() });
+    ///
+    /// building.transform_root(RootId(0), &Transform::rotation_around([0.0, 90.0, 0.0], [0.0; 3]));
+    /// assert_eq!(building.blocks[0].position.map(f32::round), [2.0, 0.0, 0.0]);
+    /// assert_eq!(building.blocks[0].rotation, [0.0, 90.0, 0.0]);
+    /// ```
+    pub fn transform_root(&mut self, root: RootId, transform: &Transform) {
+        self.transform_roots(&[root], transform);
+    }
+
+    /// Moves and rotates a root and every root it carries, directly or not,
+    /// by `transform`, see [`RootGraph::descendants`](crate::hierarchy::RootGraph::descendants).
+    pub fn transform_subassembly(&mut self, root: RootId, transform: &Transform) {
+        let roots = self.root_graph().descendants(root);
+        self.transform_roots(&roots, transform);
+    }
+
+    /// Moves a root and its blocks to a new world transform, keeping the
+    /// blocks' transforms relative to the root.
+    pub fn set_root_transform(&mut self, root: RootId, transform: &Transform) {
+        let Some(current) = self.root(root).map(Root::transform) else {
+            return;
+        };
+        self.transform_root(root, &current.inverse().then(transform));
+        // Set exactly, rather than through the composed transform.
+        let root = &mut self.roots[root.index()];
+        root.position = transform.translation;
+        root.rotation = transform.rotation;
+    }
+
+    fn transform_roots(&mut self, roots: &[RootId], transform: &Transform) {
+        let mut selected = vec![false; self.roots.len()];
+        for root in roots {
+            if let Some(selected) = selected.get_mut(root.index()) {
+                *selected = true;
+            }
+        }
+        for (root, _) in self.roots.iter_mut().zip(&selected).filter(|(_, selected)| **selected) {
+            root.position = transform.apply_position(root.position);
+            root.rotation = transform.apply_rotation(root.rotation);
+        }
+        for block in self.blocks.iter_mut() {
+            if selected.get(block.root as usize).copied().unwrap_or(false) {
+                block.position = transform.apply_position(block.position);
+                block.rotation = transform.apply_rotation(block.rotation);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// A coordinate axis.
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    /// Index of the axis in a `[f32; 3]` vector.
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl Building {
+    /// Moves and rotates every root and block by `transform`.
+    pub fn transform(&mut self, transform: &Transform) {
+        for root in self.roots.iter_mut() {
+            root.position = transform.apply_position(root.position);
+            root.rotation = transform.apply_rotation(root.rotation);
+        }
+        for block in self.blocks.iter_mut() {
+            block.position = transform.apply_position(block.position);
+            block.rotation = transform.apply_rotation(block.rotation);
+        }
+    }
+
+    /// Moves every root and block by `offset`.
+    pub fn translate(&mut self, offset: [f32; 3]) {
+        self.transform(&Transform::from_translation(offset));
+    }
+
+    /// Rotates every root and block by `rotation` (Euler angles in degrees)
+    /// around `pivot`.
+    ///
+    /// Rotations are composed as rotations, not by adding angles, so blocks
+    /// keep their orientation relative to each other.
+    pub fn rotate(&mut self, rotation: [f32; 3], pivot: [f32; 3]) {
+        self.transform(&Transform::rotation_around(rotation, pivot));
+    }
+
+    /// Rotates every root and block by `turns` quarter turns around `axis`,
+    /// through the origin.
+    ///
+    /// Positions are permuted and negated rather than multiplied, so they
+    /// stay exact. Rotations are canonicalized with [`math::canonical_euler`],
+    /// so axis-aligned rotations stay exact as well.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    /// use sw_structure_io::transform::Axis;
+    ///
+    /// let mut building = Building::default();
+    /// building.blocks.push(Block { position: [1.0, 2.0, 3.0], rotation: [90.0, 0.0, 0.0], ..Default::default() });
+    ///
+    /// building.rotate_quarter(Axis::Z, 1);
+    /// assert_eq!(building.blocks[0].position, [-2.0, 1.0, 3.0]);
+    /// assert_eq!(building.blocks[0].rotation, [0.0, 90.0, 90.0]);
+    /// ```
+    pub fn rotate_quarter(&mut self, axis: Axis, turns: i32) {
+        let turns = turns.rem_euclid(4);
+        if turns == 0 {
+            return;
+        }
+        let mut rotation = [0.0; 3];
+        rotation[axis.index()] = 90.0 * turns as f32;
+        let transform = Transform::from_rotation(rotation);
+
+        let quarter = |position: [f32; 3]| (0..turns).fold(position, |p, _| quarter_turn(axis, p));
+        let turn = |angles: [f32; 3]| math::canonical_euler(transform.apply_rotation(angles));
+
+        for root in self.roots.iter_mut() {
+            root.position = quarter(root.position);
+            root.rotation = turn(root.rotation);
+        }
+        for block in self.blocks.iter_mut() {
+            block.position = quarter(block.position);
+            block.rotation = turn(block.rotation);
+        }
+    }
+
+    /// Moves every root and block by the smallest offset that puts the first
+    /// block on the grid of spacing `step` through `origin`, and returns the
+    /// offset.
+    ///
+    /// The building moves as a whole, so blocks keep their exact positions
+    /// relative to each other; [`Building::snap_to_grid`] snaps every block on
+    /// its own instead. Buildings without blocks are left as they are.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    ///
+    /// let mut building = Building::default();
+    /// building.blocks.push(Block { position: [0.25, 1.0, -0.75], ..Default::default() });
+    /// building.blocks.push(Block { position: [1.5, 1.0, -0.75], ..Default::default() });
+    ///
+    /// let offset = building.align_to_grid(1.0, [0.0, 0.0, 0.5]);
+    /// assert_eq!(offset, [-0.25, 0.0, 0.25]);
+    /// assert_eq!(building.blocks[0].position, [0.0, 1.0, -0.5]);
+    /// assert_eq!(building.blocks[1].position, [1.25, 1.0, -0.5]);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `step` is not positive.
+    pub fn align_to_grid(&mut self, step: f32, origin: [f32; 3]) -> [f32; 3] {
+        assert!(step > 0.0, "grid step (is {step}) should be positive");
+
+        let Some(anchor) = self.blocks.first().map(|block| block.position) else {
+            return [0.0; 3];
+        };
+        let offset = [0, 1, 2].map(|i| {
+            let from_origin = anchor[i] - origin[i];
+            math::positive_zero((from_origin / step).round() * step - from_origin)
+        });
+        self.translate(offset);
+        offset
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Block type ids that turn into another type when mirrored.
+///
+/// Most blocks are symmetric left to right and keep their type when
+/// mirrored. Chiral blocks (e.g. left and right wedges) have a mirrored
+/// counterpart, which [`Building::mirror`] swaps them for.
+pub struct MirrorTable {
+    pairs: HashMap<u8, u8>,
+}
+
+impl MirrorTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `a` and `b` as mirrored counterparts of each other.
+    pub fn with_pair(mut self, a: u8, b: u8) -> Self {
+        self.pairs.insert(a, b);
+        self.pairs.insert(b, a);
+        self
+    }
+
+    /// Type id of the mirrored counterpart of `id`.
+    pub fn mirror_id(&self, id: u8) -> u8 {
+        self.pairs.get(&id).copied().unwrap_or(id)
+    }
+}
+
+impl Building {
+    /// Mirrors every root and block across the plane through `origin` whose
+    /// normal is `axis`.
+    ///
+    /// A reflection can not be expressed as a rotation, so blocks are
+    /// assumed to be symmetric left to right (across their local X axis):
+    /// their forward and up axes are mirrored, and their local X axis is
+    /// reversed. Chiral blocks are swapped using `table`. Rotations are
+    /// canonicalized with [`math::canonical_euler`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    /// use sw_structure_io::transform::{Axis, MirrorTable};
+    ///
+    /// let mut building = Building::default();
+    /// building.blocks.push(Block { id: 10, position: [2.0, 0.0, 1.0], rotation: [0.0, 30.0, 0.0], ..Default::default() });
+    ///
+    /// building.mirror(Axis::X, 0.0, &MirrorTable::new().with_pair(10, 11));
+    /// assert_eq!(building.blocks[0].position, [-2.0, 0.0, 1.0]);
+    /// assert_eq!(building.blocks[0].rotation, [0.0, 330.0, 0.0]);
+    /// assert_eq!(building.blocks[0].id, 11);
+    /// ```
+    pub fn mirror(&mut self, axis: Axis, origin: f32, table: &MirrorTable) {
+        let a = axis.index();
+        let reflect = |mut position: [f32; 3]| {
+            position[a] = math::positive_zero(2.0 * origin - position[a]);
+            position
+        };
+        let turn = |rotation: [f32; 3]| math::canonical_euler(math::to_euler(math::mirror(math::from_euler(rotation), axis)));
+
+        for root in self.roots.iter_mut() {
+            root.position = reflect(root.position);
+            root.rotation = turn(root.rotation);
+        }
+        for block in self.blocks.iter_mut() {
+            block.position = reflect(block.position);
+            block.rotation = turn(block.rotation);
+            block.id = table.mirror_id(block.id);
+        }
+    }
+}
+
+/// Rotates a position by +90° around `axis`, exactly.
+fn quarter_turn(axis: Axis, [x, y, z]: [f32; 3]) -> [f32; 3] {
+    let neg = |v: f32| math::positive_zero(-v);
+    match axis {
+        Axis::X => [x, neg(z), y],
+        Axis::Y => [z, y, neg(x)],
+        Axis::Z => [neg(y), x, z],
+    }
+}
+
+impl Building {
+    /// Rewrites every root and block rotation with
+    /// [`math::canonical_euler`], so equal orientations are stored equally.
+    ///
+    /// Returns the number of rotations that changed.
+    pub fn canonicalize_rotations(&mut self) -> usize {
+        self.map_rotations(|rotation| Some(math::canonical_euler(rotation)))
+    }
+
+    /// Snaps every root and block rotation within `tolerance` degrees of an
+    /// axis-aligned orientation to it, see [`math::snap_to_axes`].
+    ///
+    /// Returns the number of rotations that changed.
+    pub fn snap_rotations(&mut self, tolerance: f32) -> usize {
+        self.map_rotations(|rotation| math::snap_to_axes(rotation, tolerance))
+    }
+
+    fn map_rotations(&mut self, mut f: impl FnMut([f32; 3]) -> Option<[f32; 3]>) -> usize {
+        let rotations = self.roots
+            .iter_mut()
+            .map(|root| &mut root.rotation)
+            .chain(self.blocks.iter_mut().map(|block| &mut block.rotation));
+
+        let mut changed = 0;
+        for rotation in rotations {
+            if let Some(new) = f(*rotation).filter(|new| new != rotation) {
+                *rotation = new;
+                changed += 1;
+            }
+        }
+        changed
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// A block moved by [`Building::snap_to_grid`] by more than the tolerance.
+pub struct Snapped {
+    pub block: BlockId,
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+}
+
+impl Snapped {
+    /// Distance the block moved.
+    pub fn distance(&self) -> f32 {
+        (0..3).map(|i| (self.to[i] - self.from[i]).powi(2)).sum::<f32>().sqrt()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Result of [`Building::snap_to_grid`].
+pub struct SnapReport {
+    /// Number of blocks whose position changed.
+    pub changed: usize,
+
+    /// Blocks that moved by more than the tolerance, which usually means
+    /// they were not meant to be on the grid.
+    pub moved: Vec<Snapped>,
+}
+
+impl Building {
+    /// Rounds every block position to the nearest multiple of `step`, e.g.
+    /// `1.0` for whole blocks or `0.5` for half blocks.
+    ///
+    /// This removes float noise (like `0.99999994`) left by generators and
+    /// transforms, so quantized formats encode clean values. Blocks moving by
+    /// more than `tolerance` are still snapped, and are reported. Root
+    /// positions are left as they are.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    ///
+    /// let mut building = Building::default();
+    /// building.blocks.push(Block { position: [0.99999994, 2.5000002, -1.0], ..Default::default() });
+    /// building.blocks.push(Block { position: [0.3, 0.0, 0.0], ..Default::default() });
+    ///
+    /// let report = building.snap_to_grid(0.5, 1e-3);
+    /// assert_eq!(building.blocks[0].position, [1.0, 2.5, -1.0]);
+    /// assert_eq!(report.changed, 2);
+    /// assert_eq!(report.moved.len(), 1);
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `step` is not positive.
+    pub fn snap_to_grid(&mut self, step: f32, tolerance: f32) -> SnapReport {
+        assert!(step > 0.0, "grid step (is {step}) should be positive");
+
+        let mut report = SnapReport::default();
+        for (index, block) in self.blocks.iter_mut().enumerate() {
+            let snapped = block.position.map(|v| math::positive_zero((v / step).round() * step));
+            if snapped == block.position {
+                continue;
+            }
+
+            let moved = Snapped { block: BlockId(index as u16), from: block.position, to: snapped };
+            if moved.distance() > tolerance {
+                report.moved.push(moved);
+            }
+            block.position = snapped;
+            report.changed += 1;
+        }
+        report
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Where [`Building::recompute_root_transforms`] places a root.
+pub enum RootOrigin<'a> {
+    /// Leave the position as it is.
+    Keep,
+
+    /// Center of the bounds of the root's block positions.
+    BoundsCenter,
+
+    /// Center of mass of the root's blocks, weighted by `MassTable`.
+    CenterOfMass(&'a MassTable),
+
+    /// Position of the block carrying the root through `load` (its bearing,
+    /// shock absorber…), or the bounds center for roots carried by nothing.
+    Joint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How [`Building::recompute_root_transforms`] orients a root.
+pub enum RootRotation {
+    /// Leave the rotation as it is.
+    Keep,
+
+    /// No rotation.
+    Identity,
+
+    /// Rotation of the root's first block.
+    FirstBlock,
+
+    /// Orientation shared by most of the root's blocks, the first one found
+    /// on ties.
+    MostCommon,
+}
+
+impl Building {
+    /// Recomputes the position and rotation of every root from its blocks,
+    /// see [`Building::recompute_root_transform`].
+    pub fn recompute_root_transforms(&mut self, origin: RootOrigin, rotation: RootRotation) {
+        // Blocks of roots that do not exist move no root.
+        let mut blocks: Vec<Vec<&Block>> = vec![Vec::new(); self.iter_roots().count()];
+        for (_, block) in self.iter_blocks() {
+            if let Some(blocks) = blocks.get_mut(block.root as usize) {
+                blocks.push(block);
+            }
+        }
+        let graph = matches!(origin, RootOrigin::Joint).then(|| self.root_graph());
+        let transforms: Vec<_> = blocks
+            .iter()
+            .enumerate()
+            .map(|(root, blocks)| self.root_transform_of(RootId(root as u16), blocks, graph.as_ref(), origin, rotation))
+            .collect();
+        for (root, transform) in self.roots.iter_mut().zip(transforms) {
+            root.apply_recomputed(transform);
+        }
+    }
+
+    /// Recomputes the position and rotation of a root from its blocks.
+    ///
+    /// Blocks store world-space transforms, so they do not move; only their
+    /// transform relative to the root changes. Roots without blocks are left
+    /// as they are.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sw_structure_io::structs::*;
+    /// use sw_structure_io::id::RootId;
+    /// use sw_structure_io::transform::{RootOrigin, RootRotation};
+    ///
+    /// let mut building = Building::default();
+    /// building.roots.push(Root::default());
+    /// building.blocks.push(Block { position: [2.0, 0.0, 0.0], rotation: [0.0, 90.0, 0.0], ..Default::default() });
+    /// building.blocks.push(Block { position: [4.0, 2.0, 0.0], rotation: [0.0, 90.0, 0.0], ..Default::default() });
+    ///
+    /// building.recompute_root_transform(RootId(0), RootOrigin::BoundsCenter, RootRotation::MostCommon);
+    /// assert_eq!(building.roots[0].position, [3.0, 1.0, 0.0]);
+    /// assert_eq!(building.roots[0].rotation, [0.0, 90.0, 0.0]);
+    /// ```
+    pub fn recompute_root_transform(&mut self, root: RootId, origin: RootOrigin, rotation: RootRotation) {
+        let blocks: Vec<&Block> = self.blocks_of(root).map(|(_, block)| block).collect();
+        let graph = matches!(origin, RootOrigin::Joint).then(|| self.root_graph());
+        let transform = self.root_transform_of(root, &blocks, graph.as_ref(), origin, rotation);
+        if let Some(root) = self.roots.get_mut(root.index()) {
+            root.apply_recomputed(transform);
+        }
+    }
+
+    /// New position and rotation of `root` from its `blocks`, `None` where
+    /// they are kept. `graph` is only needed for [`RootOrigin::Joint`].
+    fn root_transform_of(
+        &self,
+        root: RootId,
+        blocks: &[&Block],
+        graph: Option<&RootGraph>,
+        origin: RootOrigin,
+        rotation: RootRotation,
+    ) -> (Option<[f32; 3]>, Option<[f32; 3]>) {
+        if blocks.is_empty() {
+            return (None, None);
+        }
+
+        let bounds_center = || Bounds::from_points(blocks.iter().map(|block| &block.position)).get_center_and_size().0;
+        let position = match origin {
+            RootOrigin::Keep => None,
+            RootOrigin::BoundsCenter => Some(bounds_center()),
+            RootOrigin::CenterOfMass(masses) => {
+                let properties = MassProperties::of(blocks.iter().copied(), masses, &SizeTable::default());
+                // Massless roots have no center of mass.
+                Some(if properties.mass == 0.0 { bounds_center() } else { properties.center })
+            }
+            RootOrigin::Joint => {
+                let joint = graph.and_then(|graph| graph.parents(root).next());
+                let carrier = joint.and_then(|joint| self.block(joint.block));
+                Some(carrier.map(|block| block.position).unwrap_or_else(bounds_center))
+            }
+        };
+
+        let rotation = match rotation {
+            RootRotation::Keep => None,
+            RootRotation::Identity => Some([0.0; 3]),
+            RootRotation::FirstBlock => Some(blocks[0].rotation),
+            RootRotation::MostCommon => Some(most_common_rotation(blocks)),
+        };
+        (position, rotation)
+    }
+}
+
+impl Root {
+    fn apply_recomputed(&mut self, (position, rotation): (Option<[f32; 3]>, Option<[f32; 3]>)) {
+        if let Some(position) = position {
+            self.position = position;
+        }
+        if let Some(rotation) = rotation {
+            self.rotation = rotation;
+        }
+    }
+}
+
+/// Rotation shared by most blocks, comparing orientations rather than angles.
+fn most_common_rotation(blocks: &[&Block]) -> [f32; 3] {
+    // Distinct orientations with their number of blocks, in order of appearance.
+    let mut counts: Vec<(math::Quat, [f32; 3], usize)> = Vec::new();
+    for block in blocks {
+        let q = math::from_euler(block.rotation);
+        match counts.iter_mut().find(|(other, _, _)| math::angle_between(q, *other) < 1e-3) {
+            Some((_, _, count)) => *count += 1,
+            None => counts.push((q, block.rotation, 1)),
+        }
+    }
+    // `max_by_key` keeps the last maximum, so search from the end.
+    counts.iter().rev().max_by_key(|(_, _, count)| *count).map(|(_, rotation, _)| *rotation).unwrap_or_default()
+}
@@ -0,0 +1,48 @@
+/// Disjoint sets over `0..len`, for grouping blocks into components.
+pub(crate) struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    pub(crate) fn new(len: usize) -> Self {
+        Self { parent: (0..len).collect() }
+    }
+
+    /// Representative of the set containing `i`: its lowest element.
+    pub(crate) fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // Point the whole path at the root, so later lookups are short.
+        let mut i = i;
+        while self.parent[i] != root {
+            let next = self.parent[i];
+            self.parent[i] = root;
+            i = next;
+        }
+        root
+    }
+
+    pub(crate) fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        // The lower element becomes the root, so sets are found in order.
+        self.parent[a.max(b)] = a.min(b);
+    }
+
+    /// Every set with its elements in ascending order, ordered by their
+    /// lowest element.
+    pub(crate) fn sets(&mut self) -> Vec<Vec<usize>> {
+        let mut sets: Vec<Vec<usize>> = Vec::new();
+        let mut set_of = vec![usize::MAX; self.parent.len()];
+        for i in 0..self.parent.len() {
+            let root = self.find(i);
+            if set_of[root] == usize::MAX {
+                set_of[root] = sets.len();
+                sets.push(Vec::new());
+            }
+            sets[set_of[root]].push(i);
+        }
+        sets
+    }
+}
@@ -0,0 +1,134 @@
+use crate::structs::*;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+/// A structural problem found in a `Building`.
+///
+/// Issues describe references that point outside of the building (or to
+/// places the game does not expect them to point). A building with issues
+/// may still serialize, but the game is likely to reject or misplace it.
+pub enum ValidationIssue {
+    #[error("Building has {blocks} blocks but no roots")]
+    NoRoots {
+        blocks: usize
+    },
+    #[error("Building has {count} roots, more than the format can index")]
+    TooManyRoots {
+        count: usize
+    },
+    #[error("Building has {count} blocks, more than the format can index")]
+    TooManyBlocks {
+        count: usize
+    },
+    #[error("Block {block} references root {root} which does not exist")]
+    RootOutOfRange {
+        block: usize,
+        root: u16
+    },
+    #[error("Block {block} is connected to block {target} which does not exist")]
+    ConnectionOutOfRange {
+        block: usize,
+        target: u16
+    },
+    #[error("Block {block} carries load block {target} which does not exist")]
+    LoadOutOfRange {
+        block: usize,
+        target: u16
+    },
+    #[error("Block {block} carries load block {target} from its own root")]
+    LoadOnSameRoot {
+        block: usize,
+        target: u16
+    },
+    #[error("Block {block} has field {field} referencing block {target} which does not exist")]
+    FieldOutOfRange {
+        block: usize,
+        field: usize,
+        target: u16
+    },
+    #[error("Math block {block} orders incoming block {target} which does not exist")]
+    MathOrderOutOfRange {
+        block: usize,
+        target: u8
+    },
+    #[error("Math block {block} has {order} ordered connections but {slots} slots")]
+    MathSlotsMismatch {
+        block: usize,
+        order: usize,
+        slots: usize
+    },
+}
+
+impl Building {
+    /// Checks every index reference in the building.
+    ///
+    /// Returns all found issues, an empty vector means the building is
+    /// structurally consistent. Validation does not check version-specific
+    /// limits (e.g. version 0 stores root indices as `u8`); writing the
+    /// building is the only way to check those.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        if self.roots.is_empty() && !self.blocks.is_empty() {
+            issues.push(ValidationIssue::NoRoots { blocks: self.blocks.len() });
+        }
+        if self.roots.len() > u16::MAX as usize {
+            issues.push(ValidationIssue::TooManyRoots { count: self.roots.len() });
+        }
+        if self.blocks.len() > u16::MAX as usize {
+            issues.push(ValidationIssue::TooManyBlocks { count: self.blocks.len() });
+        }
+
+        let block_count = self.blocks.len();
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            if block.root as usize >= self.roots.len() {
+                issues.push(ValidationIssue::RootOutOfRange { block: index, root: block.root });
+            }
+
+            for &target in block.connections.iter() {
+                if target as usize >= block_count {
+                    issues.push(ValidationIssue::ConnectionOutOfRange { block: index, target });
+                }
+            }
+
+            if let Some(target) = block.load {
+                match self.blocks.get(target as usize) {
+                    None => issues.push(ValidationIssue::LoadOutOfRange { block: index, target }),
+                    Some(loaded) if loaded.root == block.root => {
+                        issues.push(ValidationIssue::LoadOnSameRoot { block: index, target })
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            let Some(metadata) = &block.metadata else {
+                continue;
+            };
+
+            for (field, items) in metadata.fields.iter().enumerate() {
+                for &target in items.iter() {
+                    if target as usize >= block_count {
+                        issues.push(ValidationIssue::FieldOutOfRange { block: index, field, target });
+                    }
+                }
+            }
+
+            if let TypeSettings::MathBlock { incoming_connections_order, slots, .. } = &metadata.type_settings {
+                for &target in incoming_connections_order.iter() {
+                    if target as usize >= block_count {
+                        issues.push(ValidationIssue::MathOrderOutOfRange { block: index, target });
+                    }
+                }
+                if incoming_connections_order.len() != slots.len() {
+                    issues.push(ValidationIssue::MathSlotsMismatch {
+                        block: index,
+                        order: incoming_connections_order.len(),
+                        slots: slots.len()
+                    });
+                }
+            }
+        }
+
+        issues
+    }
+}
@@ -0,0 +1,68 @@
+use sw_structure_io::adjacency::SizeTable;
+use sw_structure_io::id::BlockId;
+use sw_structure_io::structs::*;
+
+fn block(id: u8, position: [f32; 3], rotation: [f32; 3]) -> Block {
+    Block { id, position, rotation, ..Default::default() }
+}
+
+#[test]
+fn adjacency_uses_sizes_and_rotations() {
+    // A 3x1x1 beam (type 7) along X, then turned to lie along Z.
+    let sizes = SizeTable::default().with_size(7, [3.0, 1.0, 1.0]);
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.blocks.push(block(0, [0.0, 0.0, 0.0], [0.0; 3]));
+    building.blocks.push(block(7, [2.0, 0.0, 0.0], [0.0; 3]));
+    building.blocks.push(block(0, [1.0, 1.0, 0.0], [0.0; 3]));
+    building.blocks.push(block(7, [0.0, 0.0, 2.0], [0.0, 90.0, 0.0]));
+    building.blocks.push(block(0, [1.0, 0.0, 4.0], [0.0; 3]));
+
+    let pairs = building.face_adjacency(&sizes, 1e-3);
+    assert_eq!(pairs, [
+        (BlockId(0), BlockId(1)),
+        (BlockId(0), BlockId(3)),
+        (BlockId(1), BlockId(2)),
+    ]);
+}
+
+#[test]
+fn roots_are_inferred_from_components() {
+    let sizes = SizeTable::default();
+    let mut building = Building::default();
+    building.roots.push(Root { rotation: [0.0, 45.0, 0.0], ..Default::default() });
+    // A bearing (block 1) carrying a plate (block 2) directly on top of it.
+    building.blocks.push(block(0, [0.0, 0.0, 0.0], [0.0; 3]));
+    building.blocks.push(Block { load: Some(2), ..block(0, [0.0, 1.0, 0.0], [0.0; 3]) });
+    building.blocks.push(block(0, [0.0, 2.0, 0.0], [0.0; 3]));
+    building.blocks.push(block(0, [1.0, 2.0, 0.0], [0.0; 3]));
+    building.blocks.push(block(0, [9.0, 0.0, 0.0], [0.0; 3]));
+
+    let roots = building.infer_roots(&sizes, 1e-3);
+
+    assert_eq!(roots, [
+        vec![BlockId(0), BlockId(1)],
+        vec![BlockId(2), BlockId(3)],
+        vec![BlockId(4)],
+    ]);
+    assert_eq!(building.blocks.iter().map(|b| b.root).collect::<Vec<_>>(), [0, 0, 1, 1, 2]);
+    assert_eq!(building.roots[1].position, [0.5, 2.0, 0.0]);
+    assert_eq!(building.roots[2].rotation, [0.0, 45.0, 0.0]);
+    assert!(building.validate().is_empty());
+}
+
+#[test]
+fn load_links_split_groups_joined_through_other_blocks() {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    // A bearing carrying the block above it, with a block beside each.
+    building.blocks.push(Block { load: Some(1), ..block(0, [0.0, 1.0, 0.0], [0.0; 3]) });
+    building.blocks.push(block(0, [0.0, 2.0, 0.0], [0.0; 3]));
+    building.blocks.push(block(0, [1.0, 1.0, 0.0], [0.0; 3]));
+    building.blocks.push(block(0, [1.0, 2.0, 0.0], [0.0; 3]));
+
+    let roots = building.infer_roots(&SizeTable::default(), 1e-3);
+
+    assert_eq!(roots, [vec![BlockId(0), BlockId(2)], vec![BlockId(1), BlockId(3)]]);
+    assert!(building.validate().is_empty());
+}
@@ -0,0 +1,48 @@
+use sw_structure_io::builder::{BuildError, BuildingBuilder};
+use sw_structure_io::id::BlockId;
+use sw_structure_io::validate::ValidationIssue;
+
+#[test]
+fn builder_tracks_handles() {
+    let mut builder = BuildingBuilder::new();
+    let mut base = builder.add_root();
+    let bearing = base.add_block(3, [0.0, 0.0, 0.0], [0.0, 0.0, 0.0]);
+    let base_id = base.id();
+
+    let mut arm = builder.add_root();
+    arm.position([0.0, 1.0, 0.0]);
+    let arm_id = arm.id();
+    let plate = arm.add_block(0, [0.0, 1.0, 0.0], [0.0, 90.0, 0.0]);
+    let light = builder.add_block(arm_id, 1, [1.0, 1.0, 0.0], [0.0, 0.0, 0.0]);
+
+    builder
+        .attach_load(bearing, plate)
+        .connect(bearing, light)
+        .color(light, [0, 255, 0, 255])
+        .name(bearing, "hinge");
+    let building = builder.build().unwrap();
+
+    assert_eq!(building.roots.len(), 2);
+    assert_eq!(building.roots[1].position, [0.0, 1.0, 0.0]);
+    assert_eq!(building.block(bearing).unwrap().load_id(), Some(plate));
+    assert_eq!(building.block(bearing).unwrap().connection_ids().collect::<Vec<_>>(), [light]);
+    assert_eq!(building.block(bearing).unwrap().root_id(), base_id);
+    assert_eq!(building.block(light).unwrap().color, Some([0, 255, 0, 255]));
+    assert_eq!(building.block(plate).unwrap().rotation, [0.0, 90.0, 0.0]);
+}
+
+#[test]
+fn build_reports_invalid_buildings() {
+    let mut builder = BuildingBuilder::new();
+    let mut root = builder.add_root();
+    let a = root.add_block(0, [0.0; 3], [0.0; 3]);
+    let b = root.add_block(0, [1.0, 0.0, 0.0], [0.0; 3]);
+    builder.attach_load(a, b).connect(a, BlockId(7));
+
+    let Err(BuildError::Invalid { issues, building }) = builder.build() else {
+        panic!("building should not validate");
+    };
+    assert_eq!(issues.len(), 2);
+    assert!(issues.contains(&ValidationIssue::ConnectionOutOfRange { block: 0, target: 7 }));
+    assert_eq!(building.blocks.len(), 2);
+}
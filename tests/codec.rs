@@ -0,0 +1,51 @@
+use std::io::Cursor;
+
+use sw_structure_io::io::{ReadBuilding, WriteBuilding, inspect_building};
+use sw_structure_io::structs::*;
+
+fn with_metadata(id: u8, metadata: Metadata) -> Building {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.blocks.push(Block { id, metadata: Some(metadata), ..Default::default() });
+    building
+}
+
+fn roundtrip(building: &Building) -> (Vec<u8>, Building) {
+    let mut bytes = Cursor::new(Vec::new());
+    bytes.write_building(building, 0).unwrap();
+    let bytes = bytes.into_inner();
+    let read = Cursor::new(&bytes).read_building().unwrap();
+    (bytes, read)
+}
+
+#[test]
+fn vectors_keep_the_field_count_v0() {
+    let building = with_metadata(109, Metadata {
+        vectors: vec![[0.5, 1.0, 0.0]],
+        fields: vec![vec![0], Vec::new()],
+        ..Default::default()
+    });
+    let (bytes, read) = roundtrip(&building);
+
+    // The count is offset by 0x7FFF when vectors follow, not or-ed with it.
+    let inspection = inspect_building(Cursor::new(&bytes));
+    let control = inspection.fields.iter().find(|f| f.path == "blocks[0].metadata.vector_field_control").unwrap();
+    assert_eq!(control.raw, (0x7FFFu16 + 2).to_le_bytes());
+
+    let metadata = read.blocks[0].metadata.as_ref().unwrap();
+    assert_eq!(metadata.vectors, [[0.5, 1.0, 0.0]]);
+    assert_eq!(metadata.fields, [vec![0], Vec::new()]);
+}
+
+#[test]
+fn math_block_settings_are_read_back_v0() {
+    let type_settings = TypeSettings::MathBlock {
+        function: "a+b".to_string(),
+        incoming_connections_order: vec![0],
+        slots: vec![1],
+    };
+    let building = with_metadata(129, Metadata { type_settings: type_settings.clone(), ..Default::default() });
+    let (_, read) = roundtrip(&building);
+
+    assert_eq!(read.blocks[0].metadata.as_ref().unwrap().type_settings, type_settings);
+}
@@ -0,0 +1,84 @@
+use sw_structure_io::diff::*;
+use sw_structure_io::structs::*;
+
+fn block(id: u8, x: f32) -> Block {
+    Block { id, position: [x, 0.0, 0.0], ..Default::default() }
+}
+
+fn sample_building() -> Building {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.blocks.push(block(1, 0.0));
+    building.blocks.push(Block { connections: vec![2], ..block(2, 1.0) });
+    building.blocks.push(block(3, 2.0));
+    building
+}
+
+#[test]
+fn reordering_blocks_is_not_a_change() {
+    let old = sample_building();
+    let mut new = sample_building();
+    new.blocks.swap(0, 2);
+    // Block 1 still points at the block with id 3, which is now at index 0.
+    new.blocks[1].connections = vec![0];
+
+    assert!(diff(&old, &new).is_empty(), "{}", diff(&old, &new));
+}
+
+#[test]
+fn added_removed_and_changed_blocks() {
+    let old = sample_building();
+    let mut new = sample_building();
+    new.blocks.remove(0);
+    new.blocks[0].connections = vec![0];
+    new.blocks[0].name = "Renamed".to_string();
+    new.blocks.push(block(4, 3.0));
+
+    let difference = diff(&old, &new);
+    assert!(difference.blocks.contains(&Change::Removed { old: 0 }));
+    assert!(difference.blocks.contains(&Change::Added { new: 2 }));
+
+    let Some(Change::Changed { old: 1, new: 0, fields }) = difference.blocks
+        .iter()
+        .find(|c| matches!(c, Change::Changed { .. })) else {
+        panic!("{difference}");
+    };
+    let paths: Vec<&str> = fields.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["name", "connections"]);
+}
+
+#[test]
+fn positions_within_tolerance_match_across_grid_cells() {
+    let old = sample_building();
+    let mut new = sample_building();
+    // Either side of the boundaries of a rounding and of a flooring grid.
+    new.blocks[0].position = [0.0004, 0.0, 0.0];
+    new.blocks[1].position = [1.0006, 0.0, 0.0];
+    new.blocks[2].position = [1.9998, 0.0, 0.0];
+
+    let matching = match_buildings(&old, &new);
+    assert_eq!(matching.blocks, [Some(0), Some(1), Some(2)]);
+    assert!(diff(&old, &new).is_empty(), "{}", diff(&old, &new));
+
+    // The closest block wins, not the first one within tolerance.
+    new.blocks.insert(0, block(1, -0.0009));
+    new.blocks[2].connections = vec![3];
+    assert_eq!(match_buildings(&old, &new).blocks, [Some(1), Some(2), Some(3)]);
+}
+
+#[test]
+fn non_finite_and_huge_positions_are_matched_without_a_grid() {
+    // Files can hold any `f32`, including ones whose grid cell does not fit.
+    let mut old = sample_building();
+    old.blocks[0].position = [f32::INFINITY, 0.0, 0.0];
+    old.blocks[1].position = [1e16, 0.0, 0.0];
+    old.blocks[2].position = [f32::NAN, 0.0, 0.0];
+    let mut new = old.clone();
+    new.blocks[2].position = [2.0, 0.0, 0.0];
+
+    let matching = match_buildings(&old, &new);
+    assert_eq!(matching.blocks, [Some(0), Some(1), None]);
+    let difference = diff(&old, &new);
+    assert!(difference.blocks.contains(&Change::Removed { old: 2 }));
+    assert!(difference.blocks.contains(&Change::Added { new: 2 }));
+}
@@ -0,0 +1,90 @@
+use sw_structure_io::duplicates::DedupePolicy;
+use sw_structure_io::id::BlockId;
+use sw_structure_io::structs::*;
+
+fn doubled_building() -> Building {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.roots.push(Root::default());
+    building.blocks.push(Block { connections: vec![2, 3], ..Default::default() });
+    building.blocks.push(Block { position: [1.0, 0.0, 0.0], connections: vec![0], ..Default::default() });
+    building.blocks.push(Block { position: [1.0, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { position: [1.0004, 0.0, 0.0], id: 5, connections: vec![4], ..Default::default() });
+    building.blocks.push(Block { position: [2.0, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { position: [5.0, 5.0, 5.0], root: 1, load: Some(2), ..Default::default() });
+    building.blocks.push(Block { position: [5.0, 5.0, 5.0005], root: 1, ..Default::default() });
+    building
+}
+
+#[test]
+fn duplicates_are_grouped_within_tolerance() {
+    let building = doubled_building();
+
+    let groups: Vec<Vec<BlockId>> = building.find_duplicates(1e-3).into_iter().map(|d| d.blocks).collect();
+    assert_eq!(groups, [vec![BlockId(1), BlockId(2), BlockId(3)], vec![BlockId(5), BlockId(6)]]);
+
+    let strict: Vec<Vec<BlockId>> = building.find_duplicates(1e-4).into_iter().map(|d| d.blocks).collect();
+    assert_eq!(strict, [vec![BlockId(1), BlockId(2)]]);
+}
+
+#[test]
+fn dedupe_redirects_references_to_the_kept_block() {
+    let mut building = doubled_building();
+    let dedupe = building.dedupe(1e-3, DedupePolicy::KeepFirst);
+
+    assert_eq!(dedupe.kept, [(BlockId(1), vec![BlockId(2), BlockId(3)]), (BlockId(5), vec![BlockId(6)])]);
+    assert!(dedupe.removal.deleted.is_empty());
+    assert_eq!(building.blocks.len(), 4);
+    // Both connections of block 0 now point at the kept block, once.
+    assert_eq!(building.blocks[0].connections, vec![1]);
+    // The kept block took over the connection of block 3.
+    assert_eq!(building.blocks[1].connections, vec![0, 2]);
+    assert_eq!(building.blocks[3].load, Some(1));
+    assert!(building.validate().is_empty());
+
+    let mut building = doubled_building();
+    let dedupe = building.dedupe(1e-3, DedupePolicy::KeepLast);
+    assert_eq!(dedupe.kept[0].0, BlockId(3));
+    assert_eq!(building.blocks[1].id, 5);
+
+    let mut building = doubled_building();
+    building.blocks[2].connections = vec![0, 4];
+    let dedupe = building.dedupe(1e-3, DedupePolicy::KeepMostConnected);
+    assert_eq!(dedupe.kept[0].0, BlockId(2));
+}
+
+#[test]
+fn groups_do_not_chain() {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    // Each block is within tolerance of the next, but the first and last are not.
+    for x in [0.0, 0.8, 1.6] {
+        building.blocks.push(Block { position: [x, 0.0, 0.0], ..Default::default() });
+    }
+    let groups: Vec<Vec<BlockId>> = building.find_duplicates(1.0).into_iter().map(|d| d.blocks).collect();
+    assert_eq!(groups, [vec![BlockId(0), BlockId(1)]]);
+}
+
+#[test]
+fn exact_duplicates_are_found_far_from_the_origin() {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    for x in [1000.0, 1000.5, 1000.0, 5e6, 5e6] {
+        building.blocks.push(Block { position: [x, 0.0, 0.0], ..Default::default() });
+    }
+    let groups: Vec<Vec<BlockId>> = building.find_duplicates(0.0).into_iter().map(|d| d.blocks).collect();
+    assert_eq!(groups, [vec![BlockId(0), BlockId(2)], vec![BlockId(3), BlockId(4)]]);
+}
+
+#[test]
+fn blocks_past_the_last_handle_are_reported_unchecked() {
+    // JSON does not limit the number of blocks; handles stop at `u16::MAX`.
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.blocks = (0..70000).map(|i| Block { position: [i as f32, 0.0, 0.0], ..Default::default() }).collect();
+    building.blocks[69999].position = building.blocks[69998].position;
+
+    let dedupe = building.dedupe(1e-3, DedupePolicy::KeepFirst);
+    assert_eq!(dedupe.unchecked, 65536..70000);
+    assert_eq!(building.blocks.len(), 70000);
+}
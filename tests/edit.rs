@@ -0,0 +1,260 @@
+use sw_structure_io::edit::{DeletedReference, RootBlocks};
+use sw_structure_io::id::{BlockId, RootId};
+use sw_structure_io::structs::*;
+use sw_structure_io::transform::Transform;
+
+fn math_block(order: Vec<u8>, slots: Vec<u8>) -> Block {
+    Block {
+        id: 129,
+        metadata: Some(Metadata {
+            fields: vec![vec![1, 3]],
+            type_settings: TypeSettings::MathBlock { function: "a+b".to_string(), incoming_connections_order: order, slots },
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+fn sample_building() -> Building {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.roots.push(Root::default());
+    building.blocks.push(Block { connections: vec![1, 2, 3], load: Some(3), ..math_block(vec![1, 3], vec![0, 1]) });
+    building.blocks.push(Block::default());
+    building.blocks.push(Block { connections: vec![0], ..Default::default() });
+    building.blocks.push(Block { root: 1, ..Default::default() });
+    building
+}
+
+fn order_and_slots(block: &Block) -> (Vec<u8>, Vec<u8>) {
+    match &block.metadata.as_ref().unwrap().type_settings {
+        TypeSettings::MathBlock { incoming_connections_order, slots, .. } => (incoming_connections_order.clone(), slots.clone()),
+        TypeSettings::None => panic!("not a math block"),
+    }
+}
+
+#[test]
+fn removing_blocks_renumbers_and_reports_references() {
+    let mut building = sample_building();
+    let removal = building.remove_blocks([BlockId(1)]);
+
+    assert_eq!(building.blocks.len(), 3);
+    assert_eq!(building.blocks[0].connections, vec![1, 2]);
+    assert_eq!(building.blocks[0].load, Some(2));
+    assert_eq!(building.blocks[0].metadata.as_ref().unwrap().fields, vec![vec![2]]);
+    assert_eq!(order_and_slots(&building.blocks[0]), (vec![2], vec![1]));
+    assert_eq!(removal.remap.as_slice(), [Some(0), None, Some(1), Some(2)]);
+    assert_eq!(removal.remap.get(BlockId(3)), Some(BlockId(2)));
+    assert_eq!(removal.deleted.len(), 3);
+    assert!(removal.deleted.iter().all(|d| *d == DeletedReference { block: BlockId(0), target: BlockId(1) }));
+    assert!(building.validate().is_empty());
+}
+
+#[test]
+fn retain_blocks_drops_load() {
+    let mut building = sample_building();
+    building.retain_blocks(|_, block| block.root == 0);
+
+    assert_eq!(building.blocks.len(), 3);
+    assert_eq!(building.blocks[0].load, None);
+    assert!(building.validate().is_empty());
+}
+
+#[test]
+fn retaining_every_block_keeps_blocks_past_the_last_handle() {
+    // JSON does not limit the number of blocks; handles stop at `u16::MAX`.
+    let mut building = Building { blocks: vec![Block::default(); 70000], ..Default::default() };
+    building.blocks[69999].connections = vec![1];
+
+    let removal = building.retain_blocks(|_, _| true);
+    assert_eq!(building.blocks.len(), 70000);
+    assert_eq!(building.blocks[69999].connections, vec![1]);
+    assert!(removal.deleted.is_empty());
+}
+
+#[test]
+fn inserting_a_block_shifts_references() {
+    let mut building = sample_building();
+    building.insert_block(BlockId(1), Block { connections: vec![0], ..Default::default() });
+
+    assert_eq!(building.blocks[0].connections, vec![2, 3, 4]);
+    assert_eq!(building.blocks[0].load, Some(4));
+    assert_eq!(order_and_slots(&building.blocks[0]), (vec![2, 4], vec![0, 1]));
+    assert_eq!(building.blocks[1].connections, vec![0]);
+    assert_eq!(building.blocks[3].connections, vec![0]);
+}
+
+#[test]
+fn shifting_edits_report_every_dropped_reference() {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    // The math block orders blocks 1 and 255; block 1 also points past the end.
+    building.blocks.push(math_block(vec![1, 255], vec![0, 1]));
+    building.blocks.push(Block { connections: vec![300, 0], ..Default::default() });
+    building.blocks.resize(256, Block::default());
+
+    let insertion = building.insert_block(BlockId(0), Block::default());
+    // Block 255 moved to 256, which the `u8` order can not store, and block
+    // 300 would have become an alias of block 299 after another shift.
+    assert_eq!(order_and_slots(&building.blocks[1]), (vec![2], vec![0]));
+    assert_eq!(building.blocks[2].connections, vec![1]);
+    assert_eq!(insertion.deleted, [
+        DeletedReference { block: BlockId(1), target: BlockId(255) },
+        DeletedReference { block: BlockId(2), target: BlockId(300) },
+    ]);
+
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.blocks.push(math_block(vec![1], vec![0]));
+    building.blocks.resize(300, Block::default());
+    let swap = building.swap_blocks(BlockId(1), BlockId(299));
+    assert_eq!(order_and_slots(&building.blocks[0]), (vec![], vec![]));
+    assert_eq!(swap.deleted, [DeletedReference { block: BlockId(0), target: BlockId(1) }]);
+    assert_eq!(swap.remap.get(BlockId(1)), Some(BlockId(299)));
+}
+
+#[test]
+fn swapping_blocks_swaps_references() {
+    let mut building = sample_building();
+    building.swap_blocks(BlockId(0), BlockId(3));
+
+    assert_eq!(building.blocks[3].connections, vec![1, 2, 0]);
+    assert_eq!(building.blocks[3].load, Some(0));
+    assert_eq!(building.blocks[2].connections, vec![3]);
+    assert!(building.validate().is_empty());
+}
+
+fn three_roots() -> Building {
+    let mut building = Building::default();
+    for y in 0..3 {
+        building.roots.push(Root { position: [0.0, y as f32, 0.0], ..Default::default() });
+    }
+    building.blocks.push(Block { root: 0, load: Some(1), ..Default::default() });
+    building.blocks.push(Block { root: 1, connections: vec![2], ..Default::default() });
+    building.blocks.push(Block { root: 2, position: [1.0, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { root: 2, position: [-1.0, 0.0, 0.0], ..Default::default() });
+    building
+}
+
+#[test]
+fn removing_a_root_deletes_its_blocks() {
+    let mut building = three_roots();
+    let removal = building.remove_root(RootId(1), RootBlocks::Delete);
+
+    assert_eq!(building.roots.len(), 2);
+    assert_eq!(building.blocks.iter().map(|b| b.root).collect::<Vec<_>>(), [0, 1, 1]);
+    assert_eq!(building.blocks[0].load, None);
+    assert_eq!(removal.deleted, [DeletedReference { block: BlockId(0), target: BlockId(1) }]);
+    assert!(building.validate().is_empty());
+}
+
+#[test]
+fn removing_a_root_can_move_its_blocks() {
+    let mut building = three_roots();
+    building.remove_root(RootId(0), RootBlocks::MoveTo(RootId(2)));
+
+    assert_eq!(building.roots[0].position, [0.0, 1.0, 0.0]);
+    assert_eq!(building.blocks.iter().map(|b| b.root).collect::<Vec<_>>(), [1, 0, 1, 1]);
+}
+
+#[test]
+fn merging_roots_reports_internal_loads() {
+    let mut building = three_roots();
+    let loads = building.merge_roots(RootId(1), RootId(0));
+
+    assert_eq!(building.roots.len(), 2);
+    assert_eq!(building.blocks.iter().map(|b| b.root).collect::<Vec<_>>(), [0, 0, 1, 1]);
+    assert_eq!(loads, [BlockId(0)]);
+}
+
+#[test]
+fn splitting_a_root_moves_selected_blocks() {
+    let mut building = three_roots();
+    let new_root = building.split_root(RootId(2), |_, block| block.position[0] > 0.0);
+
+    assert_eq!(new_root, RootId(3));
+    assert_eq!(building.roots[3].position, building.roots[2].position);
+    assert_eq!(building.blocks.iter().map(|b| b.root).collect::<Vec<_>>(), [0, 1, 3, 2]);
+}
+
+#[test]
+fn handles_access_roots_and_blocks() {
+    let mut building = Building::default();
+    let root = building.push_root(Root::default());
+    let a = building.push_block(Block { root: root.into(), ..Default::default() });
+    let b = building.push_block(Block { root: root.into(), connections: vec![a.into()], ..Default::default() });
+
+    assert_eq!(building.block(b).unwrap().connection_ids().collect::<Vec<_>>(), [a]);
+    assert_eq!(building.root_of(a), building.root(root));
+    assert_eq!(building.blocks_of(root).map(|(id, _)| id).collect::<Vec<_>>(), [a, b]);
+    assert!(building.block(BlockId(2)).is_none());
+}
+
+#[test]
+fn merging_buildings_offsets_references_and_transforms() {
+    let mut building = three_roots();
+    let other = sample_building();
+    let transform = Transform { translation: [10.0, 0.0, 0.0], rotation: [0.0, 90.0, 0.0] };
+    let appended = building.merge(&other, &transform);
+
+    assert_eq!(appended.roots, [RootId(3), RootId(4)]);
+    assert_eq!(appended.blocks, (4..8).map(BlockId).collect::<Vec<_>>());
+    assert_eq!(appended.dropped, 0);
+    assert_eq!(building.roots.len(), 5);
+
+    let merged = &building.blocks[4];
+    assert_eq!(merged.root, 3);
+    assert_eq!(merged.connections, vec![5, 6, 7]);
+    assert_eq!(merged.load, Some(7));
+    assert_eq!(merged.metadata.as_ref().unwrap().fields, vec![vec![5, 7]]);
+    assert_eq!(order_and_slots(merged), (vec![5, 7], vec![0, 1]));
+    assert_eq!(building.blocks[7].root, 4);
+    assert!(building.validate().is_empty());
+
+    // A block at +X ends up at -Z after a 90° turn around Y, then is moved.
+    let moved = transform.apply_position([1.0, 0.0, 0.0]);
+    assert!(moved.iter().zip([10.0, 0.0, -1.0]).all(|(a, b)| (a - b).abs() < 1e-5), "{moved:?}");
+    let rotation = transform.apply_rotation([0.0, 300.0, 0.0]);
+    assert!((rotation[1] - 30.0).abs() < 1e-3, "{rotation:?}");
+}
+
+#[test]
+fn extracting_blocks_compacts_roots_and_reports_cuts() {
+    let building = three_roots();
+    let extraction = building.extract_blocks([BlockId(1), BlockId(3)]);
+
+    assert_eq!(extraction.building.roots.len(), 2);
+    assert_eq!(extraction.building.roots[0].position, [0.0, 1.0, 0.0]);
+    assert_eq!(extraction.roots, [None, Some(RootId(0)), Some(RootId(1))]);
+    assert_eq!(extraction.building.blocks.iter().map(|b| b.root).collect::<Vec<_>>(), [0, 1]);
+    assert_eq!(extraction.remap.get(BlockId(3)), Some(BlockId(1)));
+    assert_eq!(extraction.cut, [DeletedReference { block: BlockId(0), target: BlockId(2) }]);
+    assert!(extraction.building.validate().is_empty());
+}
+
+#[test]
+fn extracting_a_region_selects_by_position() {
+    let building = three_roots();
+    let extraction = building.extract_region([0.5, -1.0, -1.0], [2.0, 1.0, 1.0]);
+
+    assert_eq!(extraction.building.blocks.len(), 1);
+    assert_eq!(extraction.building.blocks[0].position, [1.0, 0.0, 0.0]);
+    assert_eq!(extraction.roots, [None, None, Some(RootId(0))]);
+}
+
+#[test]
+fn handles_cover_fields_and_math_inputs() {
+    let block = math_block(vec![2, 0], vec![1]);
+    assert_eq!(block.field_ids(0).collect::<Vec<_>>(), [BlockId(1), BlockId(3)]);
+    assert_eq!(block.field_ids(1).count(), 0);
+    assert_eq!(block.incoming_ids().collect::<Vec<_>>(), [(BlockId(2), Some(1)), (BlockId(0), None)]);
+    assert_eq!(Block::default().incoming_ids().count(), 0);
+}
+
+#[test]
+fn iterating_stops_at_the_last_handle() {
+    // JSON does not limit the number of blocks; handles stop at `u16::MAX`.
+    let building = Building { blocks: vec![Block::default(); 70000], ..Default::default() };
+    assert_eq!(building.iter_blocks().count(), 65536);
+    assert_eq!(building.iter_blocks().last().map(|(id, _)| id), Some(BlockId(u16::MAX)));
+}
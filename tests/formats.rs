@@ -0,0 +1,553 @@
+//! Checks the format descriptions in `formats/` against the Rust codec.
+//!
+//! Every fixture is decoded with `inspect_building`, and each recorded field
+//! must exist in the description, appear in declaration order and, for
+//! fixed-size primitive fields, have the declared size. Conditional fields
+//! must be present exactly when their condition holds, and repeated fields
+//! must have the count their expression gives. Every fixture must also
+//! survive a round trip through `read_building` and `write_building`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use sw_structure_io::io::{Field, Inspection, ReadBuilding, WriteBuilding, inspect_building};
+use sw_structure_io::structs::*;
+
+const ROOT: &str = "<root>";
+
+#[derive(Default)]
+struct SchemaField {
+    name: String,
+    ty: String,
+    /// Arguments passed to the parameters of `ty`.
+    args: Vec<String>,
+    /// Fixed count; `None` when the count is an expression.
+    count: Option<usize>,
+    count_expr: Option<String>,
+    /// Conditions that must all hold for the field to be present.
+    conditions: Vec<String>,
+}
+
+#[derive(Default)]
+struct Schema {
+    types: HashMap<String, Vec<SchemaField>>,
+    bitfields: HashMap<String, Vec<String>>,
+    primitives: HashMap<&'static str, usize>,
+    /// Parameters of a type, in order.
+    params: HashMap<String, Vec<String>>,
+    /// Computed values of a type, by name.
+    values: HashMap<String, HashMap<String, String>>,
+    root: String,
+}
+
+/// Parses the subset of Kaitai Struct YAML used by `formats/*.ksy`.
+fn parse_ksy(source: &str) -> Schema {
+    let mut schema = Schema {
+        primitives: [("u1", 1), ("u2", 2), ("s4", 4), ("f4", 4)].into(),
+        root: ROOT.to_string(),
+        ..Default::default()
+    };
+
+    #[derive(PartialEq)]
+    enum Section { None, Seq, Params, Instances }
+    let mut current_type = String::new();
+    let mut section = Section::None;
+    let mut value_name = String::new();
+
+    for line in source.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let line = line.trim();
+
+        // Top-level `seq` entries are nested two levels less deep than the ones in `types`.
+        let top_level = current_type == ROOT && indent >= 2;
+
+        match (indent, line) {
+            (0, "seq:") => {
+                current_type = ROOT.to_string();
+                section = Section::Seq;
+            }
+            (0, _) => section = Section::None,
+            _ if section == Section::Seq && top_level => parse_ksy_entry(&mut schema, &current_type, line),
+            (2, _) if line.ends_with(':') => {
+                current_type = line.trim_end_matches(':').to_string();
+                section = Section::None;
+            }
+            (4, "seq:") => section = Section::Seq,
+            (4, "params:") => section = Section::Params,
+            (4, "instances:") => section = Section::Instances,
+            (4, _) => section = Section::None,
+            _ if section == Section::Seq => parse_ksy_entry(&mut schema, &current_type, line),
+            _ => match (&section, line.strip_prefix("- id: "), line.strip_prefix("value: ")) {
+                (Section::Params, Some(id), _) => {
+                    schema.params.entry(current_type.clone()).or_default().push(id.to_string());
+                }
+                (Section::Instances, _, Some(value)) => {
+                    schema.values.entry(current_type.clone()).or_default().insert(value_name.clone(), value.to_string());
+                }
+                (Section::Instances, _, _) if indent == 6 => value_name = line.trim_end_matches(':').to_string(),
+                _ => {}
+            },
+        }
+    }
+
+    // Types made only of single bits are bit sets.
+    let bitfields: Vec<String> = schema.types
+        .iter()
+        .filter(|(_, fields)| fields.iter().all(|f| f.ty == "b1"))
+        .map(|(name, _)| name.clone())
+        .collect();
+    for name in bitfields {
+        let fields = schema.types.remove(&name).unwrap();
+        schema.bitfields.insert(name, fields.into_iter().map(|f| f.name).collect());
+    }
+
+    schema
+}
+
+fn parse_ksy_entry(schema: &mut Schema, current_type: &str, line: &str) {
+    let fields = schema.types.entry(current_type.to_string()).or_default();
+    if let Some(id) = line.strip_prefix("- id: ") {
+        fields.push(SchemaField { name: id.to_string(), count: Some(1), ..Default::default() });
+        return;
+    }
+    let field = fields.last_mut().unwrap();
+    if let Some(ty) = line.strip_prefix("type: ") {
+        let (ty, args) = ty.split_once('(').unwrap_or((ty, ""));
+        field.ty = ty.to_string();
+        field.args = args.trim_end_matches(')').split(',').filter(|a| !a.is_empty()).map(|a| a.trim().to_string()).collect();
+    } else if let Some(count) = line.strip_prefix("repeat-expr: ").or(line.strip_prefix("size: ")) {
+        field.count = count.parse().ok();
+        field.count_expr = field.count.is_none().then(|| count.to_string());
+    } else if let Some(condition) = line.strip_prefix("if: ") {
+        field.conditions.push(condition.to_string());
+    }
+}
+
+/// Parses the subset of the ImHex pattern language used by `formats/*.hexpat`.
+fn parse_hexpat(source: &str) -> Schema {
+    let mut schema = Schema {
+        primitives: [("u8", 1), ("u16", 2), ("s32", 4), ("float", 4), ("char", 1)].into(),
+        root: "Building".to_string(),
+        ..Default::default()
+    };
+
+    enum Scope { None, Struct(String), Bitfield(String) }
+    let mut scope = Scope::None;
+    // Conditions of the `if (...) {` blocks around the current line.
+    let mut conditions: Vec<String> = Vec::new();
+
+    for line in source.lines() {
+        let mut line = line.trim();
+        if line.starts_with("//") || line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("struct ") {
+            scope = Scope::Struct(name.trim_end_matches(" {").to_string());
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("bitfield ") {
+            scope = Scope::Bitfield(name.trim_end_matches(" {").to_string());
+            continue;
+        }
+        if line.starts_with("};") || line.starts_with("} [[") {
+            scope = Scope::None;
+            continue;
+        }
+        if line == "}" {
+            conditions.pop();
+            continue;
+        }
+
+        // Strip a leading `if (...)`, keeping whatever is declared after it.
+        let mut condition = None;
+        if line.starts_with("if (") {
+            let mut depth = 0;
+            let end = line
+                .char_indices()
+                .find(|&(_, c)| {
+                    depth += match c { '(' => 1, ')' => -1, _ => 0 };
+                    c == ')' && depth == 0
+                })
+                .unwrap()
+                .0;
+            condition = Some(line[4..end].to_string());
+            line = line[end + 1..].trim();
+        }
+        if line == "{" {
+            conditions.extend(condition);
+            continue;
+        }
+        let Some(declaration) = line.strip_suffix(';') else {
+            continue;
+        };
+
+        match &scope {
+            Scope::None => {}
+            Scope::Bitfield(name) => {
+                let bit = declaration.split(':').next().unwrap().trim();
+                schema.bitfields.entry(name.clone()).or_default().push(bit.to_string());
+            }
+            Scope::Struct(name) => {
+                let (ty, rest) = declaration.split_once(' ').unwrap();
+                let (field, count, count_expr) = match rest.split_once('[') {
+                    Some((field, count)) => {
+                        let count = count.trim_end_matches(']');
+                        let parsed = count.parse().ok();
+                        (field, parsed, parsed.is_none().then(|| count.to_string()))
+                    }
+                    None => (rest, Some(1), None),
+                };
+                schema.types.entry(name.clone()).or_default().push(SchemaField {
+                    name: field.to_string(),
+                    ty: ty.to_string(),
+                    count,
+                    count_expr,
+                    conditions: conditions.iter().cloned().chain(condition).collect(),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    schema
+}
+
+/// Splits an inspector path into `(instance, field)` steps.
+///
+/// `blocks[2].metadata.toggles.len` becomes
+/// `[("", blocks), ("blocks[2]", metadata), ("blocks[2].metadata", toggles_len)]`,
+/// while the length of a nested list (`fields[0].len`) stays a field of the list.
+fn steps(path: &str) -> Vec<(String, String)> {
+    let segments: Vec<&str> = path.split('.').collect();
+    let mut steps: Vec<(String, String)> = Vec::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        let instance = segments[..i].join(".");
+        if *segment == "len" && i > 0 && !segments[i - 1].contains('[') {
+            let (instance, name) = steps.pop().unwrap();
+            steps.push((instance, format!("{name}_len")));
+        } else {
+            let name = segment.split('[').next().unwrap();
+            steps.push((instance, name.to_string()));
+        }
+    }
+
+    steps
+}
+
+fn check(schema: &Schema, inspection: &Inspection, fixture: &str) {
+    assert!(inspection.error.is_none(), "{fixture}: {:?}", inspection.error);
+
+    let mut last_seen: HashMap<String, usize> = HashMap::new();
+    // Every decoded instance of a described type, by path.
+    let mut instances: BTreeMap<String, String> = BTreeMap::new();
+
+    for field in inspection.fields.iter() {
+        let mut ty = schema.root.clone();
+        let steps = steps(&field.path);
+
+        for (depth, (instance, name)) in steps.iter().enumerate() {
+            instances.insert(instance.clone(), ty.clone());
+            let fields = schema.types.get(&ty)
+                .unwrap_or_else(|| panic!("{fixture}: type `{ty}` of `{instance}` is not described"));
+            let (index, declared) = fields.iter().enumerate()
+                .find(|(_, f)| &f.name == name)
+                .unwrap_or_else(|| panic!("{fixture}: `{}` has no field `{name}` in `{ty}`", field.path));
+
+            let last = last_seen.entry(instance.clone()).or_default();
+            assert!(*last <= index, "{fixture}: `{}` is declared out of order in `{ty}`", field.path);
+            *last = index;
+
+            if depth + 1 == steps.len() {
+                if let (Some(size), Some(count)) = (schema.primitives.get(declared.ty.as_str()), declared.count) {
+                    assert_eq!(field.raw.len(), size * count, "{fixture}: `{}` has the wrong size", field.path);
+                }
+                if let Some(expected) = schema.bitfields.get(&declared.ty) {
+                    let bits: Vec<&str> = field.bits.iter().map(|(name, _)| *name).collect();
+                    assert_eq!(expected, &bits, "{fixture}: bits of `{}`", field.path);
+                }
+            }
+
+            ty = declared.ty.clone();
+        }
+    }
+
+    let decoded = Decoded { schema, fields: &inspection.fields, instances };
+    for (instance, ty) in decoded.instances.iter() {
+        decoded.check_instance(instance, ty, fixture);
+    }
+}
+
+/// The instances of a decoded fixture, for evaluating the expressions of the
+/// description against them.
+struct Decoded<'a> {
+    schema: &'a Schema,
+    fields: &'a [Field],
+    instances: BTreeMap<String, String>,
+}
+
+impl Decoded<'_> {
+    /// Checks that the conditional fields of `instance` are present exactly
+    /// when their condition holds, and that repeated fields have the count
+    /// their expression gives.
+    fn check_instance(&self, instance: &str, ty: &str, fixture: &str) {
+        for declared in self.schema.types[ty].iter() {
+            let holds = declared.conditions.iter().all(|c| self.eval(c, instance, ty) != 0);
+            let indices = self.indices(instance, &declared.name);
+            // Items of a nested list are recorded under the list itself.
+            let recorded = self.recorded(instance, &declared.name)
+                .or_else(|| self.fields.iter().find(|f| f.path == instance && declared.count_expr.is_some()));
+            let nested = self.instances.keys().any(|path| child_segment(instance, path) == Some(&declared.name));
+            let present = !indices.is_empty() || recorded.is_some() || nested;
+
+            if !holds {
+                assert!(!present, "{fixture}: `{instance}.{}` is present but its condition is false", declared.name);
+                continue;
+            }
+            let Some(expr) = &declared.count_expr else {
+                assert!(present, "{fixture}: `{instance}.{}` is missing but its condition holds", declared.name);
+                continue;
+            };
+
+            let expected = self.eval(expr, instance, ty) as usize;
+            let size = self.schema.primitives.get(declared.ty.as_str()).copied().unwrap_or(1);
+            let actual = match recorded {
+                _ if !indices.is_empty() => indices.len(),
+                Some(field) => field.raw.len() / size,
+                None => 0,
+            };
+            assert_eq!(actual, expected, "{fixture}: count of `{instance}.{}` (`{expr}`)", declared.name);
+        }
+    }
+
+    /// Indices of the items of `name` in `instance`, recorded as `name[i]`.
+    fn indices(&self, instance: &str, name: &str) -> BTreeSet<usize> {
+        let leaves = self.fields.iter().map(|f| f.path.as_str());
+        leaves
+            .chain(self.instances.keys().map(String::as_str))
+            .filter_map(|path| {
+                let segment = child_segment(instance, path)?;
+                let index = segment.strip_prefix(name)?.strip_prefix('[')?.strip_suffix(']')?;
+                index.parse().ok()
+            })
+            .collect()
+    }
+
+    /// The field recorded as `name` directly in `instance`.
+    fn recorded(&self, instance: &str, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|f| {
+            let steps = steps(&f.path);
+            let (last_instance, last_name) = steps.last().unwrap();
+            last_instance == instance && last_name == name && !f.path.ends_with(']')
+        })
+    }
+
+    /// Evaluates a Kaitai or ImHex expression in `instance`, a `ty`.
+    ///
+    /// Supports integer literals, field and bit references, `parent.`,
+    /// parameters and computed values, `not`/`!`, `%`, `==` and `>=`,
+    /// applied left to right. Booleans are `0` or `1`.
+    fn eval(&self, expr: &str, instance: &str, ty: &str) -> u64 {
+        let mut tokens = expr
+            .split_whitespace()
+            .flat_map(|token| match token.strip_prefix('!') {
+                Some(rest) => vec!["!", rest],
+                None => vec![token],
+            })
+            .peekable();
+
+        let operand = |tokens: &mut std::iter::Peekable<_>| {
+            let mut negate = false;
+            loop {
+                match tokens.next() {
+                    Some("not" | "!") => negate = !negate,
+                    Some(atom) => {
+                        let value = self.value(atom, instance, ty);
+                        return if negate { u64::from(value == 0) } else { value };
+                    }
+                    None => panic!("`{expr}` is missing an operand"),
+                }
+            }
+        };
+
+        let mut value = operand(&mut tokens);
+        while let Some(op) = tokens.next() {
+            let rhs = operand(&mut tokens);
+            value = match op {
+                "%" => value % rhs,
+                "==" => u64::from(value == rhs),
+                ">=" => u64::from(value >= rhs),
+                _ => panic!("unsupported operator `{op}` in `{expr}`"),
+            };
+        }
+        value
+    }
+
+    fn value(&self, atom: &str, instance: &str, ty: &str) -> u64 {
+        if let Some(hex) = atom.strip_prefix("0x") {
+            return u64::from_str_radix(hex, 16).unwrap();
+        }
+        if let Ok(value) = atom.parse() {
+            return value;
+        }
+        if let Some(atom) = atom.strip_prefix("parent.") {
+            let parent = parent(instance);
+            return self.value(atom, parent, &self.instances[parent]);
+        }
+        if let Some((name, bit)) = atom.split_once('.') {
+            let field = self.recorded(instance, name).unwrap_or_else(|| panic!("`{instance}.{name}` is not recorded"));
+            let (_, set) = field.bits.iter().find(|(b, _)| *b == bit).unwrap_or_else(|| panic!("`{name}` has no bit `{bit}`"));
+            return u64::from(*set);
+        }
+        if let Some(field) = self.recorded(instance, atom) {
+            return field.raw.iter().rev().fold(0, |value, &byte| value << 8 | u64::from(byte));
+        }
+        if let Some(expr) = self.schema.values.get(ty).and_then(|values| values.get(atom)) {
+            return self.eval(expr, instance, ty);
+        }
+        if let Some(position) = self.schema.params.get(ty).and_then(|params| params.iter().position(|p| p == atom)) {
+            // The argument is passed by the field of the parent holding this instance.
+            let parent = parent(instance);
+            let parent_ty = &self.instances[parent];
+            let name = instance[instance.rfind('.').map_or(0, |i| i + 1)..].split('[').next().unwrap();
+            let field = self.schema.types[parent_ty].iter().find(|f| f.name == name).unwrap();
+            return self.eval(&field.args[position], parent, parent_ty);
+        }
+        panic!("cannot resolve `{atom}` in `{instance}`, a `{ty}`")
+    }
+}
+
+/// The instance holding `instance`.
+fn parent(instance: &str) -> &str {
+    instance.rsplit_once('.').map_or("", |(parent, _)| parent)
+}
+
+/// The segment of `path` right below `instance`, if `path` is directly inside it.
+fn child_segment<'a>(instance: &str, path: &'a str) -> Option<&'a str> {
+    let rest = if instance.is_empty() { path } else { path.strip_prefix(instance)?.strip_prefix('.')? };
+    (!rest.contains('.')).then_some(rest)
+}
+
+/// Building files in `tests/fixtures/v0`.
+fn fixture_paths_v0() -> impl Iterator<Item = PathBuf> {
+    fs::read_dir("tests/fixtures/v0")
+        .expect("fixtures directory is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "structure"))
+}
+
+fn check_fixtures(schema: &Schema) {
+    let mut checked = 0;
+    for path in fixture_paths_v0() {
+        let inspection = inspect_building(fs::File::open(&path).unwrap());
+        check(schema, &inspection, &path.display().to_string());
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
+
+#[test]
+fn kaitai_matches_codec_v0() {
+    let schema = parse_ksy(&fs::read_to_string("formats/sw_structure_v0.ksy").unwrap());
+    check_fixtures(&schema);
+}
+
+#[test]
+fn imhex_matches_codec_v0() {
+    let schema = parse_hexpat(&fs::read_to_string("formats/sw_structure_v0.hexpat").unwrap());
+    check_fixtures(&schema);
+}
+
+#[test]
+fn fixtures_roundtrip_through_codec_v0() {
+    for path in fixture_paths_v0() {
+        let bytes = fs::read(&path).unwrap();
+        let building = Cursor::new(&bytes).read_building()
+            .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+
+        let mut written = Cursor::new(Vec::new());
+        written.write_building(&building, 0).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        let written = written.into_inner();
+        assert_eq!(written, bytes, "{}: bytes differ after a round trip", path.display());
+        assert_eq!(Cursor::new(&written).read_building().unwrap(), building, "{}", path.display());
+    }
+}
+
+/// The buildings `tests/fixtures/v0` holds, see the README there.
+fn fixtures_v0() -> Vec<(&'static str, Building)> {
+    let mut basic = Building::default();
+    basic.roots.push(Root { position: [0.0, 1.0, 0.0], rotation: [0.0, 90.0, 0.0] });
+    basic.roots.push(Root { position: [2.0, 1.0, 0.0], rotation: [0.0, 0.0, 0.0] });
+    basic.blocks.push(Block { id: 0, position: [0.0, 0.0, 0.0], ..Default::default() });
+    basic.blocks.push(Block {
+        id: 5,
+        position: [1.0, 0.0, 0.0],
+        rotation: [0.0, 90.0, 180.0],
+        name: "Bearing".into(),
+        load: Some(2),
+        color: Some([10, 20, 30, 255]),
+        enable_state: 1.0,
+        ..Default::default()
+    });
+    basic.blocks.push(Block { id: 0, root: 1, position: [2.0, 0.0, 0.0], connections: vec![0, 1], enable_state_current: 3.0, ..Default::default() });
+
+    let mut metadata = Building::default();
+    metadata.roots.push(Root::default());
+    metadata.blocks.push(Block {
+        id: 109,
+        metadata: Some(Metadata {
+            toggles: vec![true, false, true],
+            values: vec![0.25, 4.0],
+            vectors: vec![[0.0, 0.5, 0.5], [1.0, 0.0, 1.0]],
+            dropdowns: vec![2],
+            colors: vec![[255, 0, 0, 255]],
+            gradients: vec![Gradient {
+                color_keys: vec![[0, 0, 0, 255], [255, 255, 255, 255]],
+                color_time_keys: vec![0.0, 1.0],
+                alpha_keys: vec![1.0],
+                alpha_time_keys: vec![0.0],
+            }],
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    metadata.blocks.push(Block {
+        id: 40,
+        position: [1.0, 0.0, 0.0],
+        connections: vec![2],
+        metadata: Some(Metadata { fields: vec![vec![0], vec![], vec![2, 0]], ..Default::default() }),
+        ..Default::default()
+    });
+    metadata.blocks.push(Block {
+        id: 129,
+        position: [2.0, 0.0, 0.0],
+        name: "Sum".into(),
+        metadata: Some(Metadata {
+            values: vec![1.0],
+            type_settings: TypeSettings::MathBlock { function: "a+b".into(), incoming_connections_order: vec![1, 0], slots: vec![0, 1] },
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+
+    let mut vectors_and_fields = Building::default();
+    vectors_and_fields.roots.push(Root::default());
+    vectors_and_fields.blocks.push(Block {
+        id: 109,
+        metadata: Some(Metadata { vectors: vec![[0.5, 1.0, 0.0]], fields: vec![vec![0], vec![]], ..Default::default() }),
+        ..Default::default()
+    });
+
+    vec![("basic", basic), ("metadata", metadata), ("vectors_and_fields", vectors_and_fields)]
+}
+
+#[test]
+#[ignore = "rewrites tests/fixtures/v0"]
+fn generate_fixtures_v0() {
+    for (name, building) in fixtures_v0() {
+        let mut file = fs::File::create(format!("tests/fixtures/v0/{name}.structure")).unwrap();
+        file.write_building(&building, 0).unwrap();
+    }
+}
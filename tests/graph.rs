@@ -0,0 +1,72 @@
+use sw_structure_io::id::BlockId;
+use sw_structure_io::structs::*;
+
+fn ids(indices: &[u16]) -> Vec<BlockId> {
+    indices.iter().map(|&i| BlockId(i)).collect()
+}
+
+fn logic(connections: &[&[u16]]) -> Building {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    for targets in connections {
+        building.blocks.push(Block { connections: targets.to_vec(), ..Default::default() });
+    }
+    building
+}
+
+#[test]
+fn cycles_and_components_are_found() {
+    // 0 -> 1 <-> 2 -> 3, 4 feeds itself, and 5 points at a missing block.
+    let building = logic(&[&[1], &[2], &[1, 3, 3], &[], &[4], &[9]]);
+    let graph = building.connection_graph();
+
+    assert_eq!(graph.outgoing(BlockId(2)).collect::<Vec<_>>(), ids(&[1, 3]));
+    assert_eq!(graph.incoming(BlockId(1)).collect::<Vec<_>>(), ids(&[0, 2]));
+    assert_eq!(graph.outgoing(BlockId(5)).count(), 0);
+    assert_eq!(graph.strongly_connected(), [ids(&[0]), ids(&[1, 2]), ids(&[3]), ids(&[4]), ids(&[5])]);
+    assert_eq!(graph.cycles(), [ids(&[1, 2]), ids(&[4])]);
+    assert!(graph.has_cycles());
+    assert_eq!(graph.topological_order(), None);
+    assert_eq!(graph.reachable_from(BlockId(2)), ids(&[1, 2, 3]));
+    assert_eq!(graph.reachable_from(BlockId(40)), []);
+    assert_eq!(graph.unconnected_outputs(|block| block != BlockId(3)), ids(&[5]));
+}
+
+#[test]
+fn acyclic_graphs_are_ordered_and_exported() {
+    let mut building = logic(&[&[2], &[2], &[], &[0]]);
+    building.roots.push(Root::default());
+    building.blocks[1].root = 1;
+    building.blocks[1].name = "say \"hi\"".to_string();
+    let graph = building.connection_graph();
+
+    assert!(!graph.has_cycles());
+    assert_eq!(graph.topological_order(), Some(ids(&[1, 3, 0, 2])));
+
+    let dot = graph.to_dot(&building);
+    assert!(dot.starts_with("digraph connections {\n"));
+    assert!(dot.contains("    subgraph cluster_root_1 {\n        label=\"root 1\";\n        1 [label=\"1: say \\\"hi\\\"\"];\n    }\n"));
+    assert!(dot.contains("        0 [label=\"0: type 0\"];\n"));
+    assert!(dot.contains("    3 -> 0;\n"));
+    assert_eq!(dot.matches("->").count(), 3);
+}
+
+#[test]
+fn blocks_outside_clusters_are_labelled_alike() {
+    let mut building = logic(&[&[1], &[]]);
+    building.blocks[1].root = 7;
+    building.blocks[1].name = "lamp".to_string();
+    let dot = building.connection_graph().to_dot(&building);
+    assert!(dot.contains("\n    1 [label=\"1: lamp\"];\n"));
+}
+
+#[test]
+fn blocks_past_the_last_handle_are_left_out() {
+    // JSON does not limit the number of blocks; handles stop at `u16::MAX`.
+    let mut building = logic(&[]);
+    building.blocks = (0..70000).map(|i| Block { connections: vec![(i % 65536) as u16], ..Default::default() }).collect();
+    let graph = building.connection_graph();
+    assert_eq!(graph.len(), 65536);
+    assert_eq!(graph.cycles().len(), 65536);
+    assert_eq!(graph.topological_order(), None);
+}
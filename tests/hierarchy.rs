@@ -0,0 +1,45 @@
+use sw_structure_io::hierarchy::Joint;
+use sw_structure_io::id::{BlockId, RootId};
+use sw_structure_io::structs::*;
+
+fn building(roots: usize, blocks: &[(u16, Option<u16>)]) -> Building {
+    let mut building = Building { roots: vec![Root::default(); roots], ..Default::default() };
+    for &(root, load) in blocks {
+        building.blocks.push(Block { root, load, ..Default::default() });
+    }
+    building
+}
+
+#[test]
+fn tree_is_traversed_from_the_base() {
+    // Root 1 is the base: it carries roots 0 and 3, and root 0 carries root 2.
+    let building = building(4, &[(0, Some(2)), (1, Some(0)), (2, None), (3, None), (1, Some(3))]);
+    let graph = building.root_graph();
+
+    assert_eq!(graph.base(), Some(RootId(1)));
+    assert_eq!(graph.traverse(), [RootId(1), RootId(0), RootId(3), RootId(2)]);
+    assert_eq!(graph.parents(RootId(2)).collect::<Vec<_>>(), [&Joint {
+        block: BlockId(0),
+        load: BlockId(2),
+        parent: RootId(0),
+        child: RootId(2),
+    }]);
+    assert!(graph.is_tree());
+    assert!(graph.cycles().is_empty());
+    assert!(graph.disconnected().is_empty());
+    assert_eq!(building.subassembly(RootId(0)), [BlockId(0), BlockId(2)]);
+}
+
+#[test]
+fn cycles_and_disconnected_roots_are_reported() {
+    // Roots 1 and 2 carry each other from the base, root 3 carries itself,
+    // root 4 is linked to nothing.
+    let building = building(5, &[(0, Some(1)), (1, Some(2)), (2, Some(1)), (3, Some(3)), (4, None)]);
+
+    let graph = building.root_graph();
+    assert_eq!(graph.base(), Some(RootId(0)));
+    assert_eq!(graph.cycles(), [vec![RootId(1), RootId(2)], vec![RootId(3)]]);
+    assert_eq!(graph.disconnected(), [RootId(3), RootId(4)]);
+    assert_eq!(graph.traverse(), [RootId(0), RootId(1), RootId(2)]);
+    assert!(!graph.is_tree());
+}
@@ -0,0 +1,74 @@
+use sw_structure_io::io::{WriteBuilding, inspect_building};
+use sw_structure_io::structs::*;
+
+fn sample_building() -> Building {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.roots.push(Root::default());
+    building.blocks.push(Block {
+        name: "Math".to_string(),
+        id: 129,
+        connections: vec![1],
+        load: Some(1),
+        color: Some([255, 0, 0, 255]),
+        metadata: Some(Metadata {
+            toggles: vec![true, false],
+            values: vec![0.5],
+            fields: vec![vec![1]],
+            type_settings: TypeSettings::MathBlock {
+                function: "a+b".to_string(),
+                incoming_connections_order: vec![1],
+                slots: vec![0],
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    building.blocks.push(Block { root: 1, ..Default::default() });
+    building
+}
+
+#[test]
+fn fields_cover_the_whole_file() {
+    let mut buffer = vec![];
+    buffer.write_building(&sample_building(), 0).unwrap();
+
+    let inspection = inspect_building(&buffer[..]);
+    assert!(inspection.error.is_none(), "{:?}", inspection.error);
+    assert_eq!(inspection.version, Some(0));
+    assert_eq!(inspection.trailing, 0);
+
+    let mut offset = 0;
+    for field in inspection.fields.iter() {
+        assert_eq!(field.range.start, offset, "gap before {}", field.path);
+        assert_eq!(field.raw, buffer[field.range.clone()]);
+        offset = field.range.end;
+    }
+    assert_eq!(offset, buffer.len());
+}
+
+#[test]
+fn block_flags_are_broken_down_into_bits() {
+    let mut buffer = vec![];
+    buffer.write_building(&sample_building(), 0).unwrap();
+
+    let inspection = inspect_building(&buffer[..]);
+    let flags = inspection.fields.iter().find(|f| f.path == "blocks[0].flags").unwrap();
+
+    assert_eq!(flags.bits.len(), 8);
+    assert_eq!(flags.bits[0], ("has_name", true));
+    assert_eq!(flags.bits[2], ("no_metadata", false));
+    assert_eq!(flags.bits[4], ("no_load", false));
+    assert!(inspection.fields.iter().any(|f| f.path == "blocks[0].metadata.type_settings.function"));
+}
+
+#[test]
+fn truncated_file_keeps_decoded_fields() {
+    let mut buffer = vec![];
+    buffer.write_building(&sample_building(), 0).unwrap();
+    buffer.truncate(60);
+
+    let inspection = inspect_building(&buffer[..]);
+    assert!(inspection.error.is_some());
+    assert!(inspection.fields.iter().any(|f| f.path == "roots[1].rotation"));
+}
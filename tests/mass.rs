@@ -0,0 +1,63 @@
+use sw_structure_io::adjacency::SizeTable;
+use sw_structure_io::mass::MassTable;
+use sw_structure_io::structs::*;
+
+fn assert_close(a: [f32; 3], b: [f32; 3]) {
+    assert!(a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-3), "{a:?} != {b:?}");
+}
+
+fn diagonal(m: [[f32; 3]; 3]) -> [f32; 3] {
+    [m[0][0], m[1][1], m[2][2]]
+}
+
+#[test]
+fn roots_and_building_are_summed_separately() {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.roots.push(Root::default());
+    building.blocks.push(Block { position: [0.0, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { position: [2.0, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { root: 1, position: [1.0, 6.0, 0.0], ..Default::default() });
+
+    let report = building.mass_properties(&MassTable::default(), &SizeTable::default());
+
+    let first = report.roots[0];
+    assert_eq!(first.mass, 2.0);
+    assert_close(first.center, [1.0, 0.0, 0.0]);
+    // Two unit cubes, each one unit away from the center along X.
+    assert_close(diagonal(first.inertia), [1.0 / 3.0, 7.0 / 3.0, 7.0 / 3.0]);
+    assert_close(first.inertia[0], [1.0 / 3.0, 0.0, 0.0]);
+
+    assert_eq!(report.roots[1].mass, 1.0);
+    assert_close(report.roots[1].center, [1.0, 6.0, 0.0]);
+    assert_eq!(report.building.mass, 3.0);
+    assert_close(report.building.center, [1.0, 2.0, 0.0]);
+}
+
+#[test]
+fn inertia_follows_block_size_and_rotation() {
+    let masses = MassTable::new(0.0).with_mass(7, 12.0);
+    let sizes = SizeTable::default().with_size(7, [3.0, 1.0, 1.0]);
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    // A beam along X turned to lie along Z, and a massless block.
+    building.blocks.push(Block { id: 7, position: [5.0, 0.0, 0.0], rotation: [0.0, 90.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { id: 1, position: [9.0, 9.0, 9.0], ..Default::default() });
+
+    let report = building.mass_properties(&masses, &sizes);
+
+    assert_eq!(report.building.mass, 12.0);
+    assert_close(report.building.center, [5.0, 0.0, 0.0]);
+    assert_close(diagonal(report.building.inertia), [10.0, 10.0, 2.0]);
+    assert_eq!(building.mass_properties(&MassTable::new(0.0), &sizes).building.center, [0.0; 3]);
+}
+
+#[test]
+fn builtin_masses_are_overridable_estimates() {
+    let masses = MassTable::default();
+    assert_eq!(masses, MassTable::builtin());
+    // A structural block, and a part that is not one.
+    assert_eq!(masses.mass(0), 1.0);
+    assert_eq!(masses.mass(129), 0.5);
+    assert_eq!(masses.with_mass(129, 2.0).mass(129), 2.0);
+}
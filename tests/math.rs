@@ -0,0 +1,113 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use sw_structure_io::id::BlockId;
+use sw_structure_io::math;
+use sw_structure_io::structs::*;
+use sw_structure_io::transform::{Axis, Transform};
+
+fn assert_close<const N: usize>(a: [f32; N], b: [f32; N]) {
+    assert!(a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-4), "{a:?} != {b:?}");
+}
+
+const ANGLES: [[f32; 3]; 6] = [
+    [0.0, 0.0, 0.0],
+    [30.0, 45.0, 60.0],
+    [350.0, 10.0, 200.0],
+    [90.0, 30.0, 0.0],
+    [270.0, 120.0, 0.0],
+    [15.0, 300.0, 90.0],
+];
+
+#[test]
+fn euler_angles_follow_the_unity_order() {
+    // +90° around Y turns forward into right, +90° around X turns forward into down.
+    assert_close(math::rotate(math::from_euler([0.0, 90.0, 0.0]), [0.0, 0.0, 1.0]), [1.0, 0.0, 0.0]);
+    assert_close(math::rotate(math::from_euler([90.0, 0.0, 0.0]), [0.0, 0.0, 1.0]), [0.0, -1.0, 0.0]);
+
+    // Z is applied first, Y last.
+    let composed = math::mul(
+        math::mul(math::from_axis_angle(Axis::Y, 45.0), math::from_axis_angle(Axis::X, 30.0)),
+        math::from_axis_angle(Axis::Z, 60.0),
+    );
+    assert_close(composed, math::from_euler([30.0, 45.0, 60.0]));
+
+    for euler in ANGLES {
+        assert_close(math::to_euler(math::from_euler(euler)), euler);
+        assert_close(math::matrix_to_euler(math::euler_to_matrix(euler)), euler);
+
+        let q = math::from_euler(euler);
+        let back = math::from_matrix(math::to_matrix(q));
+        let sign = if back[3] * q[3] < 0.0 { -1.0 } else { 1.0 };
+        assert_close(back.map(|v| v * sign), q);
+
+        let m = math::to_matrix(q);
+        assert_close(math::mat_mul(m, math::transpose(m)).concat().try_into().unwrap(), [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+    }
+}
+
+#[test]
+fn transforms_compose_and_invert() {
+    let a = Transform { translation: [1.0, 2.0, 3.0], rotation: [30.0, 45.0, 60.0] };
+    let b = Transform { translation: [-4.0, 0.5, 2.0], rotation: [10.0, 200.0, 5.0] };
+    let point = [0.3, -1.0, 2.5];
+
+    assert_close(a.then(&b).apply_position(point), b.apply_position(a.apply_position(point)));
+    assert_close(a.inverse().apply_position(a.apply_position(point)), point);
+    assert_close(math::compose_euler(a.rotation, b.rotation), a.then(&b).rotation);
+
+    let mut building = Building::default();
+    building.roots.push(Root { position: [0.0, 5.0, 0.0], rotation: [0.0, 90.0, 0.0] });
+    building.blocks.push(Block { position: [1.0, 5.0, 0.0], rotation: [0.0, 90.0, 0.0], ..Default::default() });
+
+    let local = building.block_to_root(BlockId(0)).unwrap();
+    assert_close(local.translation, [0.0, 0.0, 1.0]);
+    assert_close(local.rotation, [0.0, 0.0, 0.0]);
+    assert_close(local.then(&building.roots[0].transform()).translation, building.blocks[0].position);
+    assert!(building.block_to_root(BlockId(1)).is_none());
+}
+
+#[test]
+fn canonical_rotations_are_unique() {
+    for euler in ANGLES {
+        let canonical = math::canonical_euler(euler);
+        assert_eq!(math::canonical_euler(canonical), canonical);
+        assert!(math::angle_between(math::from_euler(euler), math::from_euler(canonical)) < 1e-2);
+    }
+    assert_eq!(math::canonical_euler([90.0, 45.0, 45.0]), [90.0, 0.0, 0.0]);
+    assert_eq!(math::canonical_euler([360.0, -180.0, 180.0]), [0.0, 180.0, 180.0]);
+    assert_eq!(math::canonical_euler([180.0, 0.0, 0.0]), [0.0, 180.0, 180.0]);
+}
+
+#[test]
+fn canonical_rotations_are_fixed_points() {
+    let mut rng = StdRng::seed_from_u64(40);
+    for _ in 0..10000 {
+        let euler = [0; 3].map(|_| rng.random_range(-720.0..720.0));
+        let canonical = math::canonical_euler(euler);
+        assert_eq!(math::canonical_euler(canonical), canonical, "{euler:?}");
+        assert!(math::angle_between(math::from_euler(euler), math::from_euler(canonical)) < 1e-2);
+    }
+}
+
+#[test]
+fn snapping_finds_the_24_axis_aligned_orientations() {
+    let orientations = math::axis_aligned_orientations();
+    assert_eq!(orientations.len(), 24);
+    assert!(orientations.iter().all(|o| o.iter().all(|a| a % 90.0 == 0.0)));
+
+    assert_eq!(math::snap_to_axes([1.5, 89.0, 179.5], 3.0), Some(math::canonical_euler([0.0, 90.0, 180.0])));
+    assert_eq!(math::snap_to_axes([20.0, 0.0, 0.0], 3.0), None);
+
+    let mut building = Building::default();
+    building.roots.push(Root { rotation: [0.0, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { rotation: [180.0, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { rotation: [359.5, 90.4, 0.0], ..Default::default() });
+    building.blocks.push(Block { rotation: [30.0, 0.0, 0.0], ..Default::default() });
+
+    // Block 1 is already canonical, and is not rewritten by float noise.
+    assert_eq!(building.canonicalize_rotations(), 1);
+    assert_eq!(building.canonicalize_rotations(), 0);
+    assert_eq!(building.blocks[0].rotation, [0.0, 180.0, 180.0]);
+    assert_eq!(building.snap_rotations(1.0), 1);
+    assert_eq!(building.blocks[1].rotation, [0.0, 90.0, 0.0]);
+    assert_eq!(building.blocks[2].rotation, [30.0, 0.0, 0.0]);
+}
@@ -0,0 +1,122 @@
+use sw_structure_io::diff::diff;
+use sw_structure_io::patch::*;
+use sw_structure_io::structs::*;
+
+fn block(id: u8, x: f32) -> Block {
+    Block { id, position: [x, 0.0, 0.0], ..Default::default() }
+}
+
+fn base_building() -> Building {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.roots.push(Root { position: [0.0, 5.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { connections: vec![2], ..block(1, 0.0) });
+    building.blocks.push(Block { load: Some(3), ..block(2, 1.0) });
+    building.blocks.push(block(3, 2.0));
+    building.blocks.push(Block { root: 1, ..block(4, 3.0) });
+    building
+}
+
+#[test]
+fn applying_computed_patch_reproduces_target() {
+    let base = base_building();
+    let mut new = base_building();
+    new.blocks.remove(0);
+    new.blocks.insert(0, Block { connections: vec![3], root: 1, ..block(9, 7.0) });
+    new.blocks[1].load = Some(3);
+    new.blocks[3].name = "Renamed".to_string();
+
+    let patch = Patch::compute(&base, &new);
+    let mut patched = base.clone();
+    patch.apply(&mut patched).unwrap();
+
+    assert!(diff(&patched, &new).is_empty(), "{}", diff(&patched, &new));
+    assert!(patched.validate().is_empty());
+}
+
+#[test]
+fn patch_survives_json() {
+    let base = base_building();
+    let mut new = base_building();
+    new.blocks.push(Block { connections: vec![0, 3], ..block(5, 4.0) });
+
+    let patch = Patch::compute(&base, &new);
+    let json = serde_json::to_string(&patch).unwrap();
+    assert_eq!(serde_json::from_str::<Patch>(&json).unwrap(), patch);
+}
+
+#[test]
+fn merge_combines_independent_changes() {
+    let base = base_building();
+    let mut ours = base_building();
+    ours.blocks[0].name = "Ours".to_string();
+    let mut theirs = base_building();
+    theirs.blocks[0].color = Some([1, 2, 3, 255]);
+    theirs.blocks.push(Block { connections: vec![1], ..block(6, 5.0) });
+
+    let merge = merge(&base, &ours, &theirs).unwrap();
+
+    assert!(merge.conflicts.is_empty(), "{:?}", merge.conflicts);
+    assert_eq!(merge.building.blocks.len(), 5);
+    assert_eq!(merge.building.blocks[0].name, "Ours");
+    assert_eq!(merge.building.blocks[0].color, Some([1, 2, 3, 255]));
+    assert_eq!(merge.building.blocks[4].connections, vec![1]);
+}
+
+#[test]
+fn merge_reports_conflicts_and_prefers_ours() {
+    let base = base_building();
+    let mut ours = base_building();
+    ours.blocks[1].name = "Ours".to_string();
+    ours.blocks.remove(2);
+    ours.blocks[0].connections.clear();
+    let mut theirs = base_building();
+    theirs.blocks[1].name = "Theirs".to_string();
+    theirs.blocks[1].connections = vec![2];
+
+    let merge = merge(&base, &ours, &theirs).unwrap();
+
+    assert!(merge.conflicts.contains(&Conflict::BlockChanged { block: 1, field: "name" }));
+    assert!(merge.conflicts.contains(&Conflict::BlockRemovedAndReferenced { block: 2 }));
+    assert_eq!(merge.building.blocks.len(), 3);
+    assert_eq!(merge.building.blocks[1].name, "Ours");
+    assert!(merge.building.blocks[1].connections.is_empty());
+    assert!(merge.building.validate().is_empty());
+}
+
+#[test]
+fn merge_combines_metadata_field_by_field() {
+    let mut base = base_building();
+    base.blocks[0].metadata = Some(Metadata { toggles: vec![false], values: vec![1.0], fields: vec![vec![1]], ..Default::default() });
+    let mut ours = base.clone();
+    ours.blocks[0].metadata.as_mut().unwrap().values = vec![2.0];
+    let mut theirs = base.clone();
+    theirs.blocks[0].metadata.as_mut().unwrap().toggles = vec![true];
+    theirs.blocks[0].metadata.as_mut().unwrap().fields = vec![vec![1, 3]];
+
+    let merged = merge(&base, &ours, &theirs).unwrap();
+    assert!(merged.conflicts.is_empty(), "{:?}", merged.conflicts);
+    let metadata = merged.building.blocks[0].metadata.as_ref().unwrap();
+    assert_eq!((&metadata.toggles, &metadata.values, &metadata.fields), (&vec![true], &vec![2.0], &vec![vec![1, 3]]));
+
+    theirs.blocks[0].metadata.as_mut().unwrap().values = vec![3.0];
+    let merged = merge(&base, &ours, &theirs).unwrap();
+    assert_eq!(merged.conflicts, [Conflict::BlockChanged { block: 0, field: "metadata.values" }]);
+    assert_eq!(merged.building.blocks[0].metadata.as_ref().unwrap().values, [2.0]);
+}
+
+#[test]
+fn dangling_references_of_the_new_building_are_reported() {
+    let base = base_building();
+    let mut new = base_building();
+    new.blocks[0].connections = vec![2, 40];
+    new.blocks.push(Block { load: Some(50), ..block(5, 4.0) });
+
+    let (patch, dangling) = Patch::compute_with_dangling(&base, &new);
+    assert_eq!(dangling, [DanglingReference { block: 0, target: 40 }, DanglingReference { block: 4, target: 50 }]);
+    assert_eq!(patch, Patch::compute(&base, &new));
+
+    let merged = merge(&base, &new, &base).unwrap();
+    assert!(merged.conflicts.contains(&Conflict::DanglingReference { block: 0, target: 40 }));
+    assert_eq!(merged.building.blocks[0].connections, [2]);
+}
@@ -0,0 +1,47 @@
+use sw_structure_io::bounds::Bounds;
+use sw_structure_io::id::RootId;
+use sw_structure_io::io::preview_quantization;
+use sw_structure_io::structs::*;
+
+fn two_roots() -> Building {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.roots.push(Root::default());
+    building.blocks.push(Block { root: 0, position: [0.0, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { root: 0, position: [1.0, 2.0, 0.0], rotation: [12.345, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { root: 1, position: [-3000.0, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { root: 1, position: [3000.0, 0.0, 1.0], ..Default::default() });
+    building.blocks.push(Block { root: 1, position: [0.123, 0.0, 0.5], ..Default::default() });
+    building
+}
+
+#[test]
+fn bounds_per_root_and_building() {
+    let building = two_roots();
+
+    assert_eq!(building.bounds(), Bounds { min: [-3000.0, 0.0, 0.0], max: [3000.0, 2.0, 1.0] });
+    assert_eq!(building.root_bounds(RootId(0)), Bounds { min: [0.0; 3], max: [1.0, 2.0, 0.0] });
+    assert_eq!(building.roots_bounds()[1], building.root_bounds(RootId(1)));
+    assert!(building.root_bounds(RootId(2)).is_empty());
+
+    let bounds = building.root_bounds(RootId(1));
+    assert!(bounds.contains(&[0.0, 0.0, 0.5]));
+    assert!(!bounds.contains(&[0.0, 1.0, 0.5]));
+    let restored = bounds.to_global(bounds.to_inbounds([3000.0, 0.0, 1.0]));
+    let (_, size) = bounds.get_center_and_size();
+    let step = size.map(|s| s / i16::MAX as f32);
+    assert!((0..3).all(|i| (restored[i] - [3000.0, 0.0, 1.0][i]).abs() <= step[i]), "{restored:?}");
+}
+
+#[test]
+fn preview_reports_errors_per_block() {
+    let building = two_roots();
+
+    let v0 = preview_quantization(&building, 0).unwrap();
+    assert!(v0.blocks[1].rotation > 0.0 && v0.max_rotation_error() < 0.01);
+    assert_eq!(v0.blocks_over(0.0).collect::<Vec<_>>(), [1]);
+
+    // Only versions the writer supports can be previewed.
+    assert!(preview_quantization(&building, 6).is_err());
+    assert!(preview_quantization(&building, 3).is_err());
+}
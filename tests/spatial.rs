@@ -0,0 +1,94 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use sw_structure_io::bounds::Bounds;
+use sw_structure_io::id::BlockId;
+use sw_structure_io::spatial::SpatialIndex;
+use sw_structure_io::structs::*;
+
+fn random_building(count: usize) -> Building {
+    let mut rng = StdRng::seed_from_u64(43);
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    for _ in 0..count {
+        let position = [rng.random_range(-40.0..40.0), rng.random_range(-5.0..5.0), rng.random_range(-40.0..40.0)];
+        building.blocks.push(Block { position, ..Default::default() });
+    }
+    building
+}
+
+fn brute_nearest(building: &Building, point: [f32; 3], k: usize) -> Vec<BlockId> {
+    let mut all: Vec<(f32, BlockId)> = building
+        .iter_blocks()
+        .map(|(id, b)| ((0..3).map(|i| (b.position[i] - point[i]).powi(2)).sum::<f32>().sqrt(), id))
+        .collect();
+    all.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    all.into_iter().take(k).map(|(_, id)| id).collect()
+}
+
+fn brute_box(building: &Building, bounds: &Bounds) -> Vec<BlockId> {
+    building.iter_blocks().filter(|(_, b)| bounds.contains(&b.position)).map(|(id, _)| id).collect()
+}
+
+fn check(index: &SpatialIndex, building: &Building) {
+    assert_eq!(index.len(), building.blocks.len());
+    for point in [[0.0, 0.0, 0.0], [39.0, 4.0, -39.0], [500.0, 0.0, 0.0], [-12.5, 1.0, 3.25]] {
+        for k in [1, 5, 40] {
+            assert_eq!(index.nearest(point, k), brute_nearest(building, point, k), "{point:?} k={k}");
+        }
+    }
+    for bounds in [
+        Bounds { min: [-10.0, -1.0, -10.0], max: [5.0, 1.0, 2.0] },
+        Bounds { min: [-100.0; 3], max: [100.0; 3] },
+        Bounds { min: [3.0; 3], max: [3.1; 3] },
+    ] {
+        assert_eq!(index.in_box(&bounds), brute_box(building, &bounds));
+    }
+}
+
+#[test]
+fn queries_match_linear_scans() {
+    let building = random_building(2000);
+    let index = SpatialIndex::new(&building, 1.0);
+    check(&index, &building);
+
+    let coarse = SpatialIndex::new(&building, 16.0);
+    check(&coarse, &building);
+
+    let position = building.blocks[7].position;
+    assert!(index.in_cell(index.cell_of(position)).contains(&BlockId(7)));
+}
+
+#[test]
+fn index_follows_edits() {
+    let mut building = random_building(500);
+    let mut index = SpatialIndex::new(&building, 2.0);
+
+    let removal = building.retain_blocks(|id, _| id.0 % 3 != 0);
+    index.apply_remap(&removal.remap);
+    check(&index, &building);
+
+    let inserted = Block { position: [1.0, 1.0, 1.0], ..Default::default() };
+    let insertion = building.insert_block(BlockId(10), inserted.clone());
+    index.apply_remap(&insertion.remap);
+    index.insert(BlockId(10), inserted.position);
+    check(&index, &building);
+
+    building.swap_blocks(BlockId(0), BlockId(10));
+    index.swap(BlockId(0), BlockId(10));
+    building.blocks[5].position = [30.0, 0.0, 30.0];
+    index.update(BlockId(5), [30.0, 0.0, 30.0]);
+    check(&index, &building);
+    assert_eq!(index.nearest([1.0, 1.0, 1.0], 1), [BlockId(0)]);
+}
+
+#[test]
+fn far_queries_do_not_overflow_cells() {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.blocks.push(Block { position: [1.0, 2.0, 3.0], ..Default::default() });
+    let index = SpatialIndex::new(&building, 1.0);
+
+    let huge = Bounds::from_points(&[[-3e9; 3], [3e9; 3]]);
+    assert_eq!(index.in_box(&huge), [BlockId(0)]);
+    assert_eq!(index.nearest([-3e9, 0.0, 0.0], 1), [BlockId(0)]);
+    assert_eq!(index.nearest([3e9; 3], 2), [BlockId(0)]);
+}
@@ -0,0 +1,215 @@
+use sw_structure_io::structs::*;
+use sw_structure_io::id::{BlockId, RootId};
+use sw_structure_io::math;
+use sw_structure_io::mass::MassTable;
+use sw_structure_io::transform::{Axis, MirrorTable, RootOrigin, RootRotation, SnapReport, Transform};
+
+fn assert_close(a: [f32; 3], b: [f32; 3]) {
+    assert!(a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-3), "{a:?} != {b:?}");
+}
+
+fn angle(a: [f32; 3], b: [f32; 3]) -> f32 {
+    math::angle_between(math::from_euler(a), math::from_euler(b))
+}
+
+fn sample_building() -> Building {
+    let mut building = Building::default();
+    building.roots.push(Root { position: [0.0, 1.0, 0.0], rotation: [0.0, 0.0, 0.0] });
+    building.blocks.push(Block { position: [1.0, 0.0, 0.0], rotation: [0.0, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { position: [0.0, 2.0, -1.0], rotation: [0.0, 90.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { position: [3.0, 1.0, 2.0], rotation: [30.0, 45.0, 10.0], ..Default::default() });
+    building
+}
+
+#[test]
+fn quarter_turns_are_exact_and_compose() {
+    for axis in [Axis::X, Axis::Y, Axis::Z] {
+        let original = sample_building();
+        let mut building = original.clone();
+        for _ in 0..4 {
+            building.rotate_quarter(axis, 1);
+        }
+
+        for (a, b) in building.blocks.iter().zip(original.blocks.iter()) {
+            assert_eq!(a.position, b.position, "{axis:?}");
+            assert_close(a.rotation, b.rotation);
+        }
+        assert_eq!(building.blocks[0].rotation, [0.0; 3], "{axis:?}");
+        assert_eq!(building.blocks[1].rotation, [0.0, 90.0, 0.0], "{axis:?}");
+    }
+
+    let mut building = sample_building();
+    building.rotate_quarter(Axis::X, -1);
+    assert_eq!(building.blocks[1].position, [0.0, -1.0, -2.0]);
+    assert_eq!(building.blocks[0].rotation, [270.0, 0.0, 0.0]);
+}
+
+#[test]
+fn rotations_compose_instead_of_adding_angles() {
+    let mut building = sample_building();
+    building.rotate([90.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+
+    // The root sits on the pivot and does not move.
+    assert_close(building.roots[0].position, [0.0, 1.0, 0.0]);
+    assert_close(building.roots[0].rotation, [90.0, 0.0, 0.0]);
+    assert_close(building.blocks[0].position, [1.0, 1.0, -1.0]);
+
+    // Facing +X after a yaw of 90°, then pitched around world X: that is a
+    // roll of 90° in the block's own frame, not a pitch.
+    assert_close(building.blocks[1].rotation, [0.0, 90.0, 90.0]);
+
+    // Rotating back restores every block.
+    building.transform(&Transform { translation: [0.0; 3], rotation: [-90.0, 0.0, 0.0] });
+    building.translate([0.0, 1.0, 1.0]);
+    for (a, b) in building.blocks.iter().zip(sample_building().blocks.iter()) {
+        assert_close(a.position, b.position);
+        assert_close(a.rotation, b.rotation);
+    }
+}
+
+#[test]
+fn mirroring_keeps_blocks_facing_the_mirrored_way() {
+    for axis in [Axis::X, Axis::Y, Axis::Z] {
+        let original = sample_building();
+        let mut building = original.clone();
+        building.mirror(axis, 0.5, &MirrorTable::new());
+
+        for (mirrored, block) in building.blocks.iter().zip(original.blocks.iter()) {
+            let mut expected = block.position;
+            expected[axis.index()] = 1.0 - expected[axis.index()];
+            assert_eq!(mirrored.position, expected, "{axis:?}");
+
+            // Forward and up of the mirrored block are the mirrored forward
+            // and up of the original, checked through points in front of and
+            // above the block.
+            for local in [[0.0, 0.0, 1.0], [0.0, 1.0, 0.0]] {
+                let mut point = Transform { translation: block.position, rotation: block.rotation }.apply_position(local);
+                point[axis.index()] = 1.0 - point[axis.index()];
+                let mirrored_point = Transform { translation: mirrored.position, rotation: mirrored.rotation }.apply_position(local);
+                assert_close(mirrored_point, point);
+            }
+        }
+
+        building.mirror(axis, 0.5, &MirrorTable::new());
+        for (a, b) in building.blocks.iter().zip(original.blocks.iter()) {
+            assert_eq!(a.position, b.position);
+            assert_close(a.rotation, b.rotation);
+        }
+    }
+}
+
+#[test]
+fn mirroring_swaps_chiral_blocks() {
+    let table = MirrorTable::new().with_pair(5, 6);
+    let mut building = sample_building();
+    building.blocks[0].id = 5;
+    building.blocks[1].id = 6;
+    building.mirror(Axis::Y, 0.0, &table);
+
+    assert_eq!(building.blocks.iter().map(|b| b.id).collect::<Vec<_>>(), [6, 5, 0]);
+    assert_eq!(building.blocks[1].rotation, [0.0, 90.0, 180.0]);
+}
+
+#[test]
+fn snapping_to_the_grid_reports_large_moves() {
+    let mut building = sample_building();
+    building.rotate([0.0, 90.0, 0.0], [0.0; 3]);
+    building.rotate([0.0, -90.0, 0.0], [0.0; 3]);
+    building.blocks[2].position[0] = 3.3;
+
+    let report = building.snap_to_grid(1.0, 0.01);
+    for (a, b) in building.blocks.iter().zip(sample_building().blocks.iter()) {
+        assert_eq!(a.position, b.position);
+    }
+    assert_eq!(report.moved.len(), 1);
+    assert_eq!(report.moved[0].block, BlockId(2));
+    assert_eq!(report.moved[0].to, [3.0, 1.0, 2.0]);
+    assert!((report.moved[0].distance() - 0.3).abs() < 1e-3);
+
+    assert_eq!(building.snap_to_grid(1.0, 0.01), SnapReport::default());
+}
+
+#[test]
+fn root_transforms_are_recomputed_from_blocks() {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.roots.push(Root::default());
+    building.roots.push(Root { position: [7.0, 7.0, 7.0], rotation: [0.0, 0.0, 0.0] });
+    building.blocks.push(Block { id: 1, position: [0.0, 0.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { position: [4.0, 0.0, 0.0], rotation: [90.0, 0.0, 0.0], ..Default::default() });
+    // A bearing of root 0 carrying block 3 of root 1.
+    building.blocks.push(Block { position: [4.0, 1.0, 0.0], rotation: [90.0, 0.0, 0.0], load: Some(3), ..Default::default() });
+    building.blocks.push(Block { root: 1, position: [4.0, 2.0, 0.0], rotation: [0.0, 180.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { root: 1, position: [6.0, 2.0, 0.0], ..Default::default() });
+    let blocks = building.blocks.clone();
+
+    let masses = MassTable::default().with_mass(1, 2.0);
+    building.recompute_root_transforms(RootOrigin::CenterOfMass(&masses), RootRotation::MostCommon);
+    assert_close(building.roots[0].position, [2.0, 0.25, 0.0]);
+    assert_eq!(building.roots[0].rotation, [90.0, 0.0, 0.0]);
+    assert_eq!(building.roots[1].rotation, [0.0, 180.0, 0.0]);
+    // Root 2 has no blocks.
+    assert_eq!(building.roots[2].position, [7.0, 7.0, 7.0]);
+
+    building.recompute_root_transforms(RootOrigin::Joint, RootRotation::Identity);
+    assert_eq!(building.roots[0].position, [2.0, 0.5, 0.0]);
+    assert_eq!(building.roots[1].position, [4.0, 1.0, 0.0]);
+    assert_eq!(building.roots[1].rotation, [0.0; 3]);
+    assert_eq!(building.blocks, blocks);
+    let local = building.block_to_root(BlockId(4)).unwrap();
+    assert_close(local.translation, [2.0, 1.0, 0.0]);
+}
+
+#[test]
+fn roots_carry_their_blocks_in_local_space() {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.roots.push(Root { position: [0.0, 1.0, 0.0], rotation: [0.0, 90.0, 0.0] });
+    building.roots.push(Root { position: [0.0, 1.0, 3.0], rotation: [0.0; 3] });
+    // A bearing of root 0 carries the arm (root 1), whose tip carries root 2.
+    building.blocks.push(Block { position: [0.0, 0.0, 0.0], load: Some(1), ..Default::default() });
+    building.blocks.push(Block { root: 1, position: [0.0, 1.0, 0.0], rotation: [0.0, 90.0, 0.0], ..Default::default() });
+    building.blocks.push(Block { root: 1, position: [0.0, 1.0, 2.0], rotation: [30.0, 90.0, 0.0], load: Some(3), ..Default::default() });
+    building.blocks.push(Block { root: 2, position: [0.0, 1.0, 3.0], ..Default::default() });
+
+    let local = building.blocks_local(RootId(1));
+    assert_eq!(local.len(), 2);
+    assert_close(local[1].1.translation, [-2.0, 0.0, 0.0]);
+
+    // Rotating the arm around its bearing keeps its blocks' local transforms.
+    building.transform_subassembly(RootId(1), &Transform::rotation_around([0.0, 90.0, 0.0], [0.0, 1.0, 0.0]));
+    assert_close(building.blocks[2].position, [2.0, 1.0, 0.0]);
+    assert_close(building.blocks[3].position, [3.0, 1.0, 0.0]);
+    assert_eq!(building.blocks[0].position, [0.0; 3]);
+    for (id, transform) in local {
+        assert_close(building.block_to_root(id).unwrap().translation, transform.translation);
+        assert!(angle(building.block_to_root(id).unwrap().rotation, transform.rotation) < 1e-2);
+    }
+
+    // Moving a block in local space, then the root to a new transform.
+    assert!(building.set_block_local(BlockId(1), &Transform::from_translation([0.0, 0.0, 1.0])));
+    assert_close(building.blocks[1].position, [0.0, 1.0, -1.0]);
+    building.set_root_transform(RootId(1), &Transform::from_translation([0.0, 5.0, 0.0]));
+    assert_eq!(building.roots[1].position, [0.0, 5.0, 0.0]);
+    assert_close(building.blocks[1].position, [0.0, 5.0, 1.0]);
+    assert_close(building.blocks[2].position, [-2.0, 5.0, 0.0]);
+    assert!(!building.set_block_local(BlockId(9), &Transform::default()));
+}
+
+#[test]
+fn aligning_to_the_grid_moves_the_building_as_a_whole() {
+    let mut building = sample_building();
+    building.blocks[0].position = [2.3, -0.4, 7.0];
+    let before = building.clone();
+
+    let offset = building.align_to_grid(0.5, [0.1, 0.0, 0.0]);
+    assert_close(building.blocks[0].position, [2.1, -0.5, 7.0]);
+    assert_close(offset, [-0.2, -0.1, 0.0]);
+    for (moved, block) in building.blocks.iter().zip(&before.blocks) {
+        assert_close(moved.position, [0, 1, 2].map(|i| block.position[i] + offset[i]));
+        assert_eq!(moved.rotation, block.rotation);
+    }
+    assert_close(building.roots[0].position, [0, 1, 2].map(|i| before.roots[0].position[i] + offset[i]));
+
+    assert_eq!(Building::default().align_to_grid(1.0, [0.0; 3]), [0.0; 3]);
+}
@@ -0,0 +1,29 @@
+use sw_structure_io::structs::*;
+use sw_structure_io::validate::ValidationIssue;
+
+#[test]
+fn valid_building_has_no_issues() {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.blocks.push(Block { connections: vec![1], ..Default::default() });
+    building.blocks.push(Block::default());
+
+    assert!(building.validate().is_empty());
+}
+
+#[test]
+fn dangling_references_are_reported() {
+    let mut building = Building::default();
+    building.roots.push(Root::default());
+    building.blocks.push(Block {
+        root: 3,
+        connections: vec![7],
+        load: Some(0),
+        ..Default::default()
+    });
+
+    let issues = building.validate();
+    assert!(issues.contains(&ValidationIssue::RootOutOfRange { block: 0, root: 3 }));
+    assert!(issues.contains(&ValidationIssue::ConnectionOutOfRange { block: 0, target: 7 }));
+    assert!(issues.contains(&ValidationIssue::LoadOnSameRoot { block: 0, target: 0 }));
+}